@@ -1,4 +1,4 @@
-cargo build --release --target wasm32-unknown-unknown
+cargo build --release --target wasm32-unknown-unknown -p game-of-life
 wasm-bindgen \
   target/wasm32-unknown-unknown/release/game_of_life.wasm \
   --out-dir build \
@@ -0,0 +1,150 @@
+//! Soup search: runs random soups until they settle and writes how many of
+//! each object they left.
+
+use game_of_life_core::census::SOUP_SIZE;
+use game_of_life_core::{Census, Error, Rule, Symmetry};
+
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::process;
+use std::thread;
+
+const USAGE: &str = "Usage: gol-census [OPTIONS] <COUNT>
+
+Runs COUNT random soups of 16x16 cells until they settle, and writes a
+census of the objects they left: an apgcode and a count per line, the most
+common objects first.
+
+Options:
+  -r, --rule <RULE>          rule, in B3/S23 notation [default: B3/S23]
+  -y, --symmetry <SYM>       soup symmetry, as in apgsearch: C1, C2_1, C2_2,
+                             C2_4, C4_1, C4_4, D2_+1, D2_+2, D2_x, D4_+1,
+                             D4_+2, D4_+4, D4_x1, D4_x4, D8_1 or D8_4
+                             [default: C1]
+      --seed <N>             seed of the first soup, the next ones counting up
+                             from it [default: 0]
+  -g, --generations <N>      generations a soup gets to settle [default: 10000]
+  -o, --output <FILE>        write the census to FILE instead of stdout
+  -j, --threads <N>          number of soups run at once
+                             [default: number of CPUs]
+  -h, --help                 print this message";
+
+struct Options {
+    soups: u32,
+    rule: Rule,
+    symmetry: Symmetry,
+    seed: u32,
+    generations: u32,
+    output: Option<String>,
+    threads: Option<usize>,
+}
+
+fn parse_number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value for {}: {}", name, value))
+}
+
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+    let mut soups = None;
+    let mut options = Options {
+        soups: 0,
+        rule: Rule::life(),
+        symmetry: Symmetry::C1,
+        seed: 0,
+        generations: 10_000,
+        output: None,
+        threads: None,
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| format!("Missing value for {}", name))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            "-r" | "--rule" => {
+                options.rule = value(&arg)?.parse().map_err(|e: Error| e.to_string())?
+            }
+            "-y" | "--symmetry" => {
+                options.symmetry = value(&arg)?.parse().map_err(|e: Error| e.to_string())?
+            }
+            "--seed" => options.seed = parse_number(&arg, &value(&arg)?)?,
+            "-g" | "--generations" => options.generations = parse_number(&arg, &value(&arg)?)?,
+            "-o" | "--output" => options.output = Some(value(&arg)?),
+            "-j" | "--threads" => options.threads = Some(parse_number(&arg, &value(&arg)?)?),
+            _ if arg.starts_with('-') => return Err(format!("Unknown option: {}", arg)),
+            _ if soups.is_none() => soups = Some(parse_number("COUNT", &arg)?),
+            _ => return Err(format!("Unexpected argument: {}", arg)),
+        }
+    }
+
+    options.soups = soups.ok_or_else(|| "Missing soup count".to_string())?;
+    Ok(options)
+}
+
+/// Runs the soups on `threads` threads, each taking every `threads`-th seed.
+fn run_soups(options: &Options, threads: usize) -> Census {
+    let mut census = Census::new(options.rule, options.symmetry);
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads as u32)
+            .map(|first| {
+                scope.spawn(move || {
+                    let mut census = Census::new(options.rule, options.symmetry);
+                    for soup in (first..options.soups).step_by(threads) {
+                        census.add_soup(options.seed.wrapping_add(soup), options.generations);
+                    }
+                    census
+                })
+            })
+            .collect();
+        for worker in workers {
+            census.merge(&worker.join().expect("a soup thread panicked"));
+        }
+    });
+    census
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    let threads = options
+        .threads
+        .or_else(|| thread::available_parallelism().ok().map(|n| n.get()))
+        .unwrap_or(1)
+        .max(1);
+    let census = run_soups(options, threads);
+    if census.unsettled() > 0 {
+        eprintln!(
+            "gol-census: {} soups of {}x{} cells didn't settle within {} generations",
+            census.unsettled(),
+            SOUP_SIZE,
+            SOUP_SIZE,
+            options.generations
+        );
+    }
+
+    match options.output {
+        Some(ref path) => {
+            fs::write(path, census.report()).map_err(|e| format!("Unable to write {}: {}", path, e))
+        }
+        None => io::stdout()
+            .write_all(census.report().as_bytes())
+            .map_err(|e| format!("Unable to write output: {}", e)),
+    }
+}
+
+fn main() {
+    let options = parse_args(env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("gol-census: {}", e);
+        eprintln!("Try 'gol-census --help' for more information.");
+        process::exit(2);
+    });
+    if let Err(e) = run(&options) {
+        eprintln!("gol-census: {}", e);
+        process::exit(1);
+    }
+}
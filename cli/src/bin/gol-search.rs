@@ -0,0 +1,132 @@
+//! Spaceship and oscillator search: prints the patterns found in a box as
+//! RLE, as soon as they are found.
+
+use game_of_life_core::{Error, SearchEnd, SearchEvent, ShipSearch, World};
+
+use std::env;
+use std::io::{self, Write};
+use std::process;
+
+const USAGE: &str = "Usage: gol-search [OPTIONS] <WxH>
+
+Searches a W by H box for oscillators or spaceships and prints each new one
+as RLE on stdout.
+
+Options:
+  -p, --period <N>              period [default: 2]
+  -d, --displacement <ROWS,COLS>
+                                cells moved by in a period, 0,0 for
+                                oscillators [default: 0,0]
+  -r, --rule <RULE>             rule, in B3/S23 notation [default: B3/S23]
+  -y, --symmetry <SYM>          none, horizontal, vertical, both, diagonal,
+                                90 or 180 [default: none]
+  -n, --max-nodes <N>           cell values tried before giving up
+                                [default: 1000000]
+      --every <N>               print the partial pattern on stderr every N
+                                nodes
+  -h, --help                    print this message";
+
+struct Options {
+    search: ShipSearch,
+    rule: Option<String>,
+}
+
+fn parse_number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value for {}: {}", name, value))
+}
+
+fn parse_pair<T: std::str::FromStr>(name: &str, value: &str, sep: char) -> Result<(T, T), String> {
+    let mut parts = value.splitn(2, sep);
+    match (parts.next(), parts.next()) {
+        (Some(a), Some(b)) => Ok((parse_number(name, a)?, parse_number(name, b)?)),
+        _ => Err(format!("Invalid value for {}: {}", name, value)),
+    }
+}
+
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+    let mut size = None;
+    let mut options = Options {
+        search: ShipSearch::new(2, (0, 0), 0, 0),
+        rule: None,
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| format!("Missing value for {}", name))
+        };
+        let search = &mut options.search;
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            "-p" | "--period" => search.period = parse_number(&arg, &value(&arg)?)?,
+            "-d" | "--displacement" => search.displacement = parse_pair(&arg, &value(&arg)?, ',')?,
+            "-r" | "--rule" => options.rule = Some(value(&arg)?),
+            "-y" | "--symmetry" => {
+                search.symmetry = value(&arg)?.parse().map_err(|e: Error| e.to_string())?
+            }
+            "-n" | "--max-nodes" => search.max_nodes = parse_number(&arg, &value(&arg)?)?,
+            "--every" => search.report_every = parse_number(&arg, &value(&arg)?)?,
+            _ if arg.starts_with('-') => return Err(format!("Unknown option: {}", arg)),
+            _ if size.is_none() => size = Some(parse_pair("the box size", &arg, 'x')?),
+            _ => return Err(format!("Unexpected argument: {}", arg)),
+        }
+    }
+
+    let (width, height) = size.ok_or_else(|| "Missing box size".to_string())?;
+    options.search.width = width;
+    options.search.height = height;
+    Ok(options)
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    let mut world = World::new(options.search.width as i32, options.search.height as i32);
+    if let Some(ref rule) = options.rule {
+        world.set_rule(rule.parse().map_err(|e: Error| e.to_string())?);
+    }
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let mut io_error = None;
+
+    let end = world
+        .search_ships(&options.search, |event| match event {
+            SearchEvent::Partial(rle) => {
+                eprint!("{}", rle);
+                true
+            }
+            SearchEvent::Found(rle) => match writeln!(out, "{}", rle).and_then(|_| out.flush()) {
+                Ok(()) => true,
+                Err(e) => {
+                    io_error = Some(e);
+                    false
+                }
+            },
+        })
+        .map_err(|e| e.to_string())?;
+    if let Some(e) = io_error {
+        return Err(format!("Unable to write output: {}", e));
+    }
+    if end == SearchEnd::GaveUp {
+        eprintln!(
+            "gol-search: gave up after {} nodes",
+            options.search.max_nodes
+        );
+    }
+    Ok(())
+}
+
+fn main() {
+    let options = parse_args(env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("gol-search: {}", e);
+        eprintln!("Try 'gol-search --help' for more information.");
+        process::exit(2);
+    });
+    if let Err(e) = run(&options) {
+        eprintln!("gol-search: {}", e);
+        process::exit(1);
+    }
+}
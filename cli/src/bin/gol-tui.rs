@@ -0,0 +1,405 @@
+//! Terminal viewer: draws a `World` with Unicode half blocks or braille
+//! characters and lets you edit and run it from the keyboard.
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use crossterm::style::{Attribute, Print, SetAttribute};
+use crossterm::{cursor, execute, queue, terminal};
+use game_of_life_core::{Cell, World};
+
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, Instant};
+
+const USAGE: &str = "Usage: gol-tui [-s WxH] [PATTERN]";
+
+const HELP: &str = "q quit  space play  n step  arrows move  HJKL pan  enter toggle  \
+                    z zoom  +/- speed  p/P patterns  r random  c clear";
+
+const PATTERNS_DIR: &str = "patterns";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Glyphs {
+    HalfBlocks,
+    Braille,
+}
+
+impl Glyphs {
+    /// Number of cells covered by one character, as `(rows, cols)`.
+    fn cells_per_char(self) -> (i32, i32) {
+        match self {
+            Glyphs::HalfBlocks => (2, 1),
+            Glyphs::Braille => (4, 2),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Glyphs::HalfBlocks => "half blocks",
+            Glyphs::Braille => "braille",
+        }
+    }
+}
+
+/// Bit of each dot of a braille character, indexed by `[row][col]`.
+const BRAILLE_DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+struct App {
+    world: World,
+    glyphs: Glyphs,
+    top: i32,
+    left: i32,
+    cursor: (i32, i32),
+    playing: bool,
+    delay: Duration,
+    patterns: Vec<PathBuf>,
+    pattern: Option<usize>,
+    message: String,
+    seed: u32,
+}
+
+impl App {
+    fn new(world: World) -> App {
+        let cursor = (world.height() / 2, world.width() / 2);
+        App {
+            world,
+            glyphs: Glyphs::HalfBlocks,
+            top: 0,
+            left: 0,
+            cursor,
+            playing: false,
+            delay: Duration::from_millis(100),
+            patterns: list_patterns(Path::new(PATTERNS_DIR)),
+            pattern: None,
+            message: String::new(),
+            seed: 0,
+        }
+    }
+
+    /// Size of the board area, in characters.
+    fn view_size() -> io::Result<(i32, i32)> {
+        let (cols, rows) = terminal::size()?;
+        Ok((::std::cmp::max(i32::from(rows) - 2, 1), i32::from(cols)))
+    }
+
+    /// Scrolls the viewport so that the cursor stays visible.
+    fn follow_cursor(&mut self, (lines, cols): (i32, i32)) {
+        let (cell_rows, cell_cols) = self.glyphs.cells_per_char();
+        let (rows, cols) = (lines * cell_rows, cols * cell_cols);
+        let (row, col) = self.cursor;
+        if row < self.top {
+            self.top = row;
+        } else if row >= self.top + rows {
+            self.top = row - rows + 1;
+        }
+        if col < self.left {
+            self.left = col;
+        } else if col >= self.left + cols {
+            self.left = col - cols + 1;
+        }
+    }
+
+    fn alive(&self, row: i32, col: i32) -> bool {
+        self.world.cell((row, col)) == Cell::Alive
+    }
+
+    fn glyph(&self, line: i32, x: i32) -> char {
+        let (cell_rows, cell_cols) = self.glyphs.cells_per_char();
+        let row = self.top + line * cell_rows;
+        let col = self.left + x * cell_cols;
+        match self.glyphs {
+            Glyphs::HalfBlocks => match (self.alive(row, col), self.alive(row + 1, col)) {
+                (true, true) => '█',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (false, false) => ' ',
+            },
+            Glyphs::Braille => {
+                let mut code = 0x2800;
+                for (r, dots) in BRAILLE_DOTS.iter().enumerate() {
+                    for (c, dot) in dots.iter().enumerate() {
+                        if self.alive(row + r as i32, col + c as i32) {
+                            code |= dot;
+                        }
+                    }
+                }
+                ::std::char::from_u32(code).unwrap_or(' ')
+            }
+        }
+    }
+
+    fn draw<W: Write>(&mut self, out: &mut W) -> io::Result<()> {
+        let (lines, cols) = App::view_size()?;
+        self.follow_cursor((lines, cols));
+
+        let (cell_rows, cell_cols) = self.glyphs.cells_per_char();
+        let cursor_line = (self.cursor.0 - self.top) / cell_rows;
+        let cursor_x = (self.cursor.1 - self.left) / cell_cols;
+
+        for line in 0..lines {
+            queue!(out, cursor::MoveTo(0, line as u16))?;
+            if line == cursor_line {
+                let before: String = (0..cursor_x).map(|x| self.glyph(line, x)).collect();
+                let after: String = (cursor_x + 1..cols).map(|x| self.glyph(line, x)).collect();
+                queue!(
+                    out,
+                    Print(before),
+                    SetAttribute(Attribute::Reverse),
+                    Print(self.glyph(line, cursor_x)),
+                    SetAttribute(Attribute::Reset),
+                    Print(after)
+                )?;
+            } else {
+                let text: String = (0..cols).map(|x| self.glyph(line, x)).collect();
+                queue!(out, Print(text))?;
+            }
+        }
+
+        let pattern = self
+            .pattern
+            .and_then(|i| self.patterns[i].file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let status = format!(
+            "gen {}  pop {}  {}  {}  {}ms  ({}, {})  {}  {}",
+            self.world.generations(),
+            self.world.population(),
+            self.world.rule(),
+            if self.playing { "playing" } else { "paused" },
+            self.delay.as_millis(),
+            self.cursor.0.rem_euclid(self.world.height()),
+            self.cursor.1.rem_euclid(self.world.width()),
+            pattern,
+            self.message
+        );
+        queue!(
+            out,
+            cursor::MoveTo(0, lines as u16),
+            terminal::Clear(terminal::ClearType::CurrentLine),
+            SetAttribute(Attribute::Bold),
+            Print(truncate(&status, cols)),
+            SetAttribute(Attribute::Reset),
+            cursor::MoveTo(0, lines as u16 + 1),
+            terminal::Clear(terminal::ClearType::CurrentLine),
+            Print(truncate(HELP, cols))
+        )?;
+        out.flush()
+    }
+
+    /// The cursor is not wrapped around the board so that the viewport can
+    /// keep scrolling over the torus; `World` wraps it when toggling.
+    fn move_cursor(&mut self, rows: i32, cols: i32) {
+        self.cursor = (self.cursor.0 + rows, self.cursor.1 + cols);
+    }
+
+    fn pan(&mut self, rows: i32, cols: i32) {
+        self.top += rows;
+        self.left += cols;
+        self.move_cursor(rows, cols);
+    }
+
+    fn load_pattern(&mut self, index: usize) {
+        let path = &self.patterns[index];
+        let result = fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| {
+                self.world.clear();
+                self.world
+                    .load_rle_lenient(&content)
+                    .map_err(|e| e.to_string())
+            });
+        self.message = match result {
+            Ok(warnings) => warnings
+                .iter()
+                .map(|w| w.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+            Err(e) => e,
+        };
+        self.pattern = Some(index);
+    }
+
+    fn cycle_pattern(&mut self, forward: bool) {
+        if self.patterns.is_empty() {
+            self.message = format!("No patterns found in {}/", PATTERNS_DIR);
+            return;
+        }
+        let count = self.patterns.len();
+        let next = match (self.pattern, forward) {
+            (None, true) => 0,
+            (None, false) => count - 1,
+            (Some(i), true) => (i + 1) % count,
+            (Some(i), false) => (i + count - 1) % count,
+        };
+        self.load_pattern(next);
+    }
+
+    /// Returns `false` when the user asked to quit.
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let page = 8;
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char(' ') => self.playing = !self.playing,
+            KeyCode::Char('n') | KeyCode::Char('.') => {
+                self.playing = false;
+                self.world.next_tick();
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.move_cursor(-1, 0),
+            KeyCode::Down | KeyCode::Char('j') => self.move_cursor(1, 0),
+            KeyCode::Left | KeyCode::Char('h') => self.move_cursor(0, -1),
+            KeyCode::Right | KeyCode::Char('l') => self.move_cursor(0, 1),
+            KeyCode::Char('K') => self.pan(-page, 0),
+            KeyCode::Char('J') => self.pan(page, 0),
+            KeyCode::Char('H') => self.pan(0, -page),
+            KeyCode::Char('L') => self.pan(0, page),
+            KeyCode::Enter | KeyCode::Char('t') => {
+                let (row, col) = self.cursor;
+                self.world.toggle((row, col));
+            }
+            KeyCode::Char('z') => {
+                self.glyphs = match self.glyphs {
+                    Glyphs::HalfBlocks => Glyphs::Braille,
+                    Glyphs::Braille => Glyphs::HalfBlocks,
+                };
+                self.message = format!("zoom: {}", self.glyphs.name());
+            }
+            KeyCode::Char('+') => {
+                self.delay = ::std::cmp::max(self.delay / 2, Duration::from_millis(1))
+            }
+            KeyCode::Char('-') => {
+                self.delay = ::std::cmp::min(self.delay * 2, Duration::from_secs(2))
+            }
+            KeyCode::Char('p') => self.cycle_pattern(true),
+            KeyCode::Char('P') => self.cycle_pattern(false),
+            KeyCode::Char('r') => {
+                self.seed = self.seed.wrapping_add(1);
+                self.world.randomize(0.3, self.seed);
+                self.message = format!("random soup, seed {}", self.seed);
+            }
+            KeyCode::Char('c') => self.world.clear(),
+            _ => (),
+        }
+        true
+    }
+
+    fn run<W: Write>(&mut self, out: &mut W) -> io::Result<()> {
+        let mut next_tick = Instant::now();
+        loop {
+            self.draw(out)?;
+
+            let timeout = if self.playing {
+                next_tick.saturating_duration_since(Instant::now())
+            } else {
+                Duration::from_millis(500)
+            };
+            if event::poll(timeout)? {
+                match event::read()? {
+                    Event::Key(key) if key.kind == KeyEventKind::Press && !self.handle_key(key) => {
+                        return Ok(());
+                    }
+                    Event::Resize(..) => queue!(out, terminal::Clear(terminal::ClearType::All))?,
+                    _ => (),
+                }
+            }
+            if self.playing && Instant::now() >= next_tick {
+                self.world.next_tick();
+                next_tick = Instant::now() + self.delay;
+            }
+        }
+    }
+}
+
+fn truncate(s: &str, width: i32) -> String {
+    s.chars().take(::std::cmp::max(width, 0) as usize).collect()
+}
+
+fn list_patterns(dir: &Path) -> Vec<PathBuf> {
+    let mut patterns: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "rle"))
+                .collect()
+        })
+        .unwrap_or_default();
+    patterns.sort();
+    patterns
+}
+
+/// Puts the terminal in raw mode on the alternate screen for as long as it
+/// lives.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn new<W: Write>(out: &mut W) -> io::Result<TerminalGuard> {
+        terminal::enable_raw_mode()?;
+        execute!(out, terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(TerminalGuard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen).ok();
+        terminal::disable_raw_mode().ok();
+    }
+}
+
+fn parse_args() -> Result<(Option<String>, (i32, i32)), String> {
+    let mut args = env::args().skip(1);
+    let mut pattern = None;
+    let mut size = (256, 256);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}\n\n{}", USAGE, HELP);
+                process::exit(0);
+            }
+            "-s" | "--size" => {
+                let value = args.next().ok_or("Missing value for --size")?;
+                let mut parts = value.splitn(2, 'x').map(str::parse::<i32>);
+                size = match (parts.next(), parts.next()) {
+                    (Some(Ok(w)), Some(Ok(h))) if w > 0 && h > 0 => (w, h),
+                    _ => return Err(format!("Invalid value for --size: {}", value)),
+                };
+            }
+            _ if pattern.is_none() && !arg.starts_with('-') => pattern = Some(arg),
+            _ => return Err(format!("Unexpected argument: {}", arg)),
+        }
+    }
+    Ok((pattern, size))
+}
+
+fn main() {
+    let (pattern, (width, height)) = parse_args().unwrap_or_else(|e| {
+        eprintln!("gol-tui: {}\n{}", e, USAGE);
+        process::exit(2);
+    });
+
+    let mut world = World::new(width, height);
+    if let Some(path) = pattern {
+        let result = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| {
+                world
+                    .load_rle_lenient(&content)
+                    .map(|_| ())
+                    .map_err(|e| e.to_string())
+            });
+        if let Err(e) = result {
+            eprintln!("gol-tui: {}: {}", path, e);
+            process::exit(1);
+        }
+    }
+
+    let mut app = App::new(world);
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let result = TerminalGuard::new(&mut out).and_then(|_guard| app.run(&mut out));
+    if let Err(e) = result {
+        eprintln!("gol-tui: {}", e);
+        process::exit(1);
+    }
+}
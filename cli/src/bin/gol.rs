@@ -0,0 +1,242 @@
+//! Headless runner: loads an RLE pattern, runs it for a number of
+//! generations and writes the result as RLE, PNG and/or statistics.
+
+use game_of_life_core::stream::{Mode, RleStream};
+use game_of_life_core::{Automaton, Error, Renderer, World};
+
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::process;
+
+const USAGE: &str = "Usage: gol [OPTIONS] <PATTERN>
+
+Runs an RLE pattern (a file path, or - for stdin) and prints the resulting
+pattern as RLE on stdout.
+
+Options:
+  -g, --generations <N>  number of generations to run [default: 0]
+  -r, --rule <RULE>      rule, in B3/S23 notation, WireWorld, BBM, Critters,
+                         a block rule in MS,D notation, or a Golly .rule
+                         file [default: B3/S23]
+  -t, --topology <TOPO>  torus or plane [default: torus]
+  -s, --size <WxH>       board size [default: pattern size plus a 64 cells margin]
+  -o, --output <FILE>    write the final pattern to FILE instead of stdout,
+                         as a PNG image if FILE ends in .png
+      --cell-size <N>    cell size of PNG output, in pixels [default: 4]
+      --stats            print generation, population and bounding box as
+                         tab separated values instead of the final RLE
+      --every <N>        with --stats, print a line every N generations
+      --lenient          accept sloppy RLE files, printing what was tolerated
+                         on stderr
+  -j, --threads <N>      number of threads stepping big boards
+                         [default: number of CPUs]
+  -h, --help             print this message";
+
+const MARGIN: i32 = 64;
+
+struct Options {
+    pattern: String,
+    generations: u32,
+    rule: Option<String>,
+    topology: Option<String>,
+    size: Option<(i32, i32)>,
+    output: Option<String>,
+    cell_size: u32,
+    stats: bool,
+    every: Option<u32>,
+    lenient: bool,
+    threads: Option<usize>,
+}
+
+fn parse_number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value for {}: {}", name, value))
+}
+
+fn parse_size(value: &str) -> Result<(i32, i32), String> {
+    let mut parts = value.splitn(2, 'x');
+    match (parts.next(), parts.next()) {
+        (Some(w), Some(h)) => Ok((parse_number("--size", w)?, parse_number("--size", h)?)),
+        _ => Err(format!("Invalid value for --size: {}", value)),
+    }
+}
+
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+    let mut pattern = None;
+    let mut options = Options {
+        pattern: String::new(),
+        generations: 0,
+        rule: None,
+        topology: None,
+        size: None,
+        output: None,
+        cell_size: 4,
+        stats: false,
+        every: None,
+        lenient: false,
+        threads: None,
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| format!("Missing value for {}", name))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            "-g" | "--generations" => options.generations = parse_number(&arg, &value(&arg)?)?,
+            "-r" | "--rule" => options.rule = Some(value(&arg)?),
+            "-t" | "--topology" => options.topology = Some(value(&arg)?),
+            "-s" | "--size" => options.size = Some(parse_size(&value(&arg)?)?),
+            "-o" | "--output" => options.output = Some(value(&arg)?),
+            "--cell-size" => options.cell_size = parse_number(&arg, &value(&arg)?)?,
+            "--stats" => options.stats = true,
+            "--every" => options.every = Some(parse_number(&arg, &value(&arg)?)?),
+            "--lenient" => options.lenient = true,
+            "-j" | "--threads" => options.threads = Some(parse_number(&arg, &value(&arg)?)?),
+            _ if arg.starts_with('-') && arg != "-" => {
+                return Err(format!("Unknown option: {}", arg));
+            }
+            _ if pattern.is_none() => pattern = Some(arg),
+            _ => return Err(format!("Unexpected argument: {}", arg)),
+        }
+    }
+
+    options.pattern = pattern.ok_or_else(|| "Missing pattern".to_string())?;
+    Ok(options)
+}
+
+fn open_pattern(path: &str, mode: Mode) -> Result<RleStream<Box<dyn BufRead>>, String> {
+    let reader: Box<dyn BufRead> = if path == "-" {
+        Box::new(io::BufReader::new(io::stdin()))
+    } else {
+        let file = fs::File::open(path).map_err(|e| format!("Unable to read {}: {}", path, e))?;
+        Box::new(io::BufReader::new(file))
+    };
+    RleStream::with_mode(reader, mode).map_err(|e| format!("{}: {}", path, e))
+}
+
+fn load_world(options: &Options) -> Result<World, String> {
+    let mode = if options.lenient {
+        Mode::Lenient
+    } else {
+        Mode::Strict
+    };
+    let mut rle = open_pattern(&options.pattern, mode)?;
+
+    let (width, height) = options.size.unwrap_or((
+        rle.size().0 as i32 + 2 * MARGIN,
+        rle.size().1 as i32 + 2 * MARGIN,
+    ));
+    if width <= 0 || height <= 0 {
+        return Err(format!("Invalid board size: {}x{}", width, height));
+    }
+
+    let mut world = World::new(width, height);
+    match options.rule {
+        Some(ref path) if path.ends_with(".rule") => {
+            let table =
+                fs::read_to_string(path).map_err(|e| format!("Unable to read {}: {}", path, e))?;
+            world
+                .load_rule_table(&table)
+                .map_err(|e| format!("{}: {}", path, e))?;
+        }
+        Some(ref rule) => match rule.parse::<Automaton>() {
+            Ok(automaton) => world.set_automaton(Some(automaton)),
+            Err(_) => world.set_rule(rule.parse().map_err(|e: Error| e.to_string())?),
+        },
+        None => {}
+    }
+    if let Some(ref topology) = options.topology {
+        world.set_topology(topology.parse().map_err(|e: Error| e.to_string())?);
+    }
+    if let Some(threads) = options.threads {
+        world.set_threads(threads);
+    }
+    world
+        .load_rle_stream(&mut rle, |_| {})
+        .map_err(|e| format!("{}: {}", options.pattern, e))?;
+    for warning in rle.warnings() {
+        eprintln!("gol: {}: {}", options.pattern, warning);
+    }
+    Ok(world)
+}
+
+fn write_stats<W: Write>(out: &mut W, world: &World) -> io::Result<()> {
+    match world.bounding_box() {
+        Some(b) => writeln!(
+            out,
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            world.generations(),
+            world.population(),
+            b.x,
+            b.y,
+            b.width,
+            b.height
+        ),
+        None => writeln!(out, "{}\t0\t\t\t\t", world.generations()),
+    }
+}
+
+fn write_png(path: &str, world: &World, cell_size: u32) -> Result<(), String> {
+    let width = world.width() as u32 * cell_size;
+    let height = world.height() as u32 * cell_size;
+    let mut renderer = Renderer::new(width, height, cell_size);
+    renderer.render(world);
+    image::save_buffer(
+        path,
+        renderer.framebuffer(),
+        width,
+        height,
+        image::ColorType::RGBA(8),
+    )
+    .map_err(|e| format!("Unable to write {}: {}", path, e))
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    let mut world = load_world(options)?;
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let io_error = |e: io::Error| format!("Unable to write output: {}", e);
+
+    if options.stats {
+        writeln!(out, "generation\tpopulation\tx\ty\twidth\theight").map_err(io_error)?;
+    }
+    for _ in 0..options.generations {
+        if let Some(every) = options.every {
+            if options.stats && every > 0 && world.generations() % every == 0 {
+                write_stats(&mut out, &world).map_err(io_error)?;
+            }
+        }
+        world.next_tick();
+    }
+    if options.stats {
+        write_stats(&mut out, &world).map_err(io_error)?;
+    }
+
+    match options.output {
+        Some(ref path) if path.ends_with(".png") => write_png(path, &world, options.cell_size)?,
+        Some(ref path) => fs::write(path, world.export_rle())
+            .map_err(|e| format!("Unable to write {}: {}", path, e))?,
+        None if !options.stats => write!(out, "{}", world.export_rle()).map_err(io_error)?,
+        None => (),
+    }
+    Ok(())
+}
+
+fn main() {
+    let options = parse_args(env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("gol: {}", e);
+        eprintln!("Try 'gol --help' for more information.");
+        process::exit(2);
+    });
+    if let Err(e) = run(&options) {
+        eprintln!("gol: {}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,186 @@
+//! Engine benchmarks: `cargo bench -p game-of-life-core`.
+
+use std::fs;
+use std::path::Path;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+
+use game_of_life_core::parser::parse_rle;
+use game_of_life_core::{Cell, World};
+
+const DENSITIES: [f64; 3] = [0.1, 0.35, 0.5];
+
+/// A random soup, already past its first generations so that the set of
+/// changed cells looks like the one of a running board.
+fn soup(size: i32, density: f64) -> World {
+    let mut world = World::new(size, size);
+    world.randomize(density, 42);
+    for _ in 0..8 {
+        world.next_tick();
+    }
+    world
+}
+
+/// A large, mostly empty board with a glider every 256 cells.
+fn gliders(size: i32) -> World {
+    let mut world = World::new(size, size);
+    for row in (0..size).step_by(256) {
+        for col in (0..size).step_by(256) {
+            for (r, c) in &[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)] {
+                world.set_cell((row + r, col + c), Cell::Alive);
+            }
+        }
+    }
+    world.next_tick();
+    world
+}
+
+fn patterns() -> Vec<(String, String)> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../patterns");
+    let mut patterns: Vec<_> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| {
+            let path = entry.unwrap().path();
+            let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+            (name, fs::read_to_string(&path).unwrap())
+        })
+        .collect();
+    patterns.sort();
+    patterns
+}
+
+fn pattern_world(rle: &str) -> World {
+    let (_, parsed) = parse_rle(rle.into()).unwrap();
+    let mut world = World::new(parsed.size.0 as i32 + 64, parsed.size.1 as i32 + 64);
+    world.load_rle(parsed).unwrap();
+    world
+}
+
+fn bench_next_tick(c: &mut Criterion) {
+    let mut group = c.benchmark_group("next_tick");
+    for &density in &DENSITIES {
+        let world = soup(512, density);
+        group.throughput(Throughput::Elements(512 * 512));
+        group.bench_with_input(BenchmarkId::new("soup_512", density), &world, |b, world| {
+            b.iter_batched(
+                || world.clone(),
+                |mut world| {
+                    world.next_tick();
+                    world
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+
+    #[cfg(feature = "parallel")]
+    {
+        let mut world = soup(512, 0.35);
+        world.set_threads(1);
+        group.bench_with_input(
+            BenchmarkId::new("soup_512_serial", 0.35),
+            &world,
+            |b, world| {
+                b.iter_batched(
+                    || world.clone(),
+                    |mut world| {
+                        world.next_tick();
+                        world
+                    },
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+
+    let world = gliders(2048);
+    group.throughput(Throughput::Elements(2048 * 2048));
+    group.bench_function("gliders_2048", |b| {
+        b.iter_batched(
+            || world.clone(),
+            |mut world| {
+                world.next_tick();
+                world
+            },
+            BatchSize::LargeInput,
+        )
+    });
+
+    for (name, rle) in patterns() {
+        let world = pattern_world(&rle);
+        group.throughput(Throughput::Elements(world.cells().len() as u64));
+        group.bench_with_input(BenchmarkId::new("pattern", &name), &world, |b, world| {
+            b.iter_batched(
+                || world.clone(),
+                |mut world| {
+                    world.next_tick();
+                    world
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn bench_pattern_boundaries(c: &mut Criterion) {
+    let mut group = c.benchmark_group("pattern_boundaries");
+    for &density in &DENSITIES {
+        let world = soup(512, density);
+        group.bench_with_input(BenchmarkId::new("soup_512", density), &world, |b, world| {
+            b.iter(|| world.bounding_box())
+        });
+    }
+    let world = gliders(2048);
+    group.bench_function("gliders_2048", |b| b.iter(|| world.bounding_box()));
+    group.finish();
+}
+
+fn bench_export_rle(c: &mut Criterion) {
+    let mut group = c.benchmark_group("export_rle");
+    for &density in &DENSITIES {
+        let world = soup(512, density);
+        group.bench_with_input(BenchmarkId::new("soup_512", density), &world, |b, world| {
+            b.iter(|| world.export_rle())
+        });
+    }
+    for (name, rle) in patterns() {
+        let world = pattern_world(&rle);
+        group.bench_with_input(BenchmarkId::new("pattern", &name), &world, |b, world| {
+            b.iter(|| world.export_rle())
+        });
+    }
+    group.finish();
+}
+
+fn bench_parse_rle(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse_rle");
+    for (name, rle) in patterns() {
+        group.throughput(Throughput::Bytes(rle.len() as u64));
+        group.bench_with_input(BenchmarkId::new("pattern", &name), &rle, |b, rle| {
+            b.iter(|| parse_rle(rle.as_str().into()).unwrap())
+        });
+    }
+
+    // Multi-megabyte patterns, exported from big soups.
+    group.sample_size(10);
+    for &density in &DENSITIES {
+        let mut world = World::new(2048, 2048);
+        world.randomize(density, 42);
+        let rle = world.export_rle();
+        group.throughput(Throughput::Bytes(rle.len() as u64));
+        group.bench_with_input(BenchmarkId::new("soup_2048", density), &rle, |b, rle| {
+            b.iter(|| parse_rle(rle.as_str().into()).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_next_tick,
+    bench_pattern_boundaries,
+    bench_export_rle,
+    bench_parse_rle
+);
+criterion_main!(benches);
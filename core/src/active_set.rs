@@ -0,0 +1,123 @@
+//! The set of cells `next_tick` has to look at.
+//!
+//! Indexes are bounded by the size of the board, so membership is a bitmap
+//! and the members are kept in a queue, in insertion order. Inserting and
+//! testing membership are O(1), iterating and clearing are O(members) rather
+//! than O(board), and a set is cleared and refilled every tick without
+//! allocating.
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ActiveSet {
+    bits: Vec<u64>,
+    queue: Vec<i32>,
+    capacity: usize,
+}
+
+impl ActiveSet {
+    /// An empty set accepting indexes in `0..capacity`.
+    pub fn new(capacity: usize) -> ActiveSet {
+        ActiveSet {
+            bits: vec![0; capacity.div_ceil(64)],
+            queue: Vec::new(),
+            capacity,
+        }
+    }
+
+    /// A set of every index in `0..capacity`.
+    pub fn full(capacity: usize) -> ActiveSet {
+        let mut set = ActiveSet::new(capacity);
+        set.insert_all();
+        set
+    }
+
+    /// Adds `idx`, and returns whether it was added: indexes already in the
+    /// set and indexes out of `0..capacity` are ignored.
+    pub fn insert(&mut self, idx: i32) -> bool {
+        if idx < 0 || idx as usize >= self.capacity || self.contains(idx) {
+            return false;
+        }
+        self.bits[idx as usize / 64] |= 1 << (idx % 64);
+        self.queue.push(idx);
+        true
+    }
+
+    pub fn insert_all(&mut self) {
+        for idx in 0..self.capacity as i32 {
+            self.insert(idx);
+        }
+    }
+
+    pub fn contains(&self, idx: i32) -> bool {
+        idx >= 0
+            && (idx as usize) < self.capacity
+            && self.bits[idx as usize / 64] & (1 << (idx % 64)) != 0
+    }
+
+    /// Members, in insertion order.
+    pub fn iter(&self) -> std::slice::Iter<'_, i32> {
+        self.queue.iter()
+    }
+
+    #[cfg(any(test, feature = "parallel"))]
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Empties the set, keeping its memory for the next fill.
+    pub fn clear(&mut self) {
+        for idx in self.queue.drain(..) {
+            self.bits[idx as usize / 64] = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duplicates_test() {
+        let mut set = ActiveSet::new(100);
+        assert!(set.insert(5));
+        assert!(set.insert(64));
+        assert!(!set.insert(5));
+        assert!(set.insert(0));
+        assert!(!set.insert(64));
+        assert_eq!(set.iter().copied().collect::<Vec<_>>(), vec![5, 64, 0]);
+        assert!(set.contains(64));
+        assert!(!set.contains(63));
+    }
+
+    #[test]
+    fn out_of_range_test() {
+        let mut set = ActiveSet::new(10);
+        assert!(!set.insert(-1));
+        assert!(!set.insert(10));
+        assert!(!set.insert(i32::MAX));
+        assert!(set.insert(9));
+        assert_eq!(set.len(), 1);
+        assert!(!set.contains(-1));
+        assert!(!set.contains(10));
+
+        let mut empty = ActiveSet::new(0);
+        assert!(!empty.insert(0));
+        assert_eq!(empty.len(), 0);
+    }
+
+    #[test]
+    fn clear_test() {
+        let mut set = ActiveSet::full(130);
+        assert_eq!(set.len(), 130);
+        assert!(set.contains(129));
+        set.clear();
+        assert_eq!(set.len(), 0);
+        assert!((0..130).all(|idx| !set.contains(idx)));
+
+        // The set is reusable, and keeps its memory.
+        let capacity = set.queue.capacity();
+        assert!(set.insert(129));
+        assert!(set.insert(3));
+        assert_eq!(set.iter().copied().collect::<Vec<_>>(), vec![129, 3]);
+        assert_eq!(set.queue.capacity(), capacity);
+    }
+}
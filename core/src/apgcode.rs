@@ -0,0 +1,386 @@
+//! apgcodes, the object names used by apgsearch and Catagolue.
+//!
+//! An apgcode is a prefix telling what the object is, followed by the object
+//! itself in extended Wechsler format:
+//!
+//! - `xs<population>_` for still lifes,
+//! - `xp<period>_` for oscillators,
+//! - `xq<period>_` for spaceships.
+//!
+//! The Wechsler part cuts the object in strips of 5 rows, separated by `z`.
+//! Each column of a strip is a character among `0-9a-v`, its top cell being
+//! the least significant bit. Runs of blank columns are shortened: `w` and
+//! `x` stand for 2 and 3 of them, `y` followed by `0-9a-z` for 4 to 39.
+//!
+//! Of all the phases, rotations and reflections of an object, its apgcode
+//! uses the shortest description, the lexicographically smallest on ties.
+
+use std::collections::{HashMap, HashSet};
+
+use super::parser::{Rle, RleComment, RleFirstLine, RleTag, RleTagSequence};
+use super::{Cell, Coord, Error, Rect, Result, Rule, World};
+
+/// Longest period looked for when classifying an object.
+pub const MAX_PERIOD: u32 = 1024;
+
+const CHARS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+/// The eight orientations, as `(a, b, c, d)`: column `u` of strip row `v`
+/// reads cell `(x0 + a * u + b * v, y0 + c * u + d * v)` from the matching
+/// corner `(x0, y0)` of the bounding box.
+const ORIENTATIONS: [(i32, i32, i32, i32); 8] = [
+    (1, 0, 0, 1),
+    (-1, 0, 0, 1),
+    (1, 0, 0, -1),
+    (-1, 0, 0, -1),
+    (0, 1, 1, 0),
+    (0, -1, 1, 0),
+    (0, 1, -1, 0),
+    (0, -1, -1, 0),
+];
+
+/// Names the object made of `cells` (on an infinite plane) under `rule`.
+pub fn encode(cells: &[Coord], rule: Rule) -> Result<String> {
+    let start: HashSet<Coord> = cells.iter().cloned().collect();
+    let (start_box, start_shape) = normalize(&start).ok_or(Error::NotPeriodic)?;
+
+    let mut phases = vec![start.clone()];
+    let mut current = start;
+    for period in 1..=MAX_PERIOD {
+        current = step(&current, rule);
+        let (bounds, shape) = normalize(&current).ok_or(Error::NotPeriodic)?;
+        if shape == start_shape {
+            let prefix = if bounds.x != start_box.x || bounds.y != start_box.y {
+                format!("xq{}", period)
+            } else if period == 1 {
+                format!("xs{}", cells.len())
+            } else {
+                format!("xp{}", period)
+            };
+            let wechsler = phases
+                .iter()
+                .flat_map(|phase| {
+                    let (bounds, _) = normalize(phase).unwrap();
+                    ORIENTATIONS
+                        .iter()
+                        .map(move |orientation| wechsler(phase, &bounds, *orientation))
+                })
+                .min_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)))
+                .unwrap();
+            return Ok(format!("{}_{}", prefix, wechsler));
+        }
+        phases.push(current.clone());
+    }
+    Err(Error::NotPeriodic)
+}
+
+/// Decodes an apgcode, or a bare Wechsler string, into an RLE pattern named
+/// after it.
+pub fn decode(code: &str) -> Result<Rle> {
+    let invalid = || Error::InvalidApgcode(code.to_string());
+    let wechsler = match code.find('_') {
+        Some(i) => {
+            let (prefix, number) = match (code.get(..2), code.get(2..i)) {
+                (Some(prefix), Some(number)) => (prefix, number),
+                _ => return Err(invalid()),
+            };
+            if !["xs", "xp", "xq"].contains(&prefix)
+                || number.is_empty()
+                || !number.bytes().all(|b| b.is_ascii_digit())
+            {
+                return Err(invalid());
+            }
+            &code[i + 1..]
+        }
+        None => code,
+    };
+
+    let mut cells = Vec::new();
+    for (v, strip) in wechsler.split('z').enumerate() {
+        let mut u = 0;
+        let mut chars = strip.bytes();
+        while let Some(c) = chars.next() {
+            match c {
+                b'w' => u += 2,
+                b'x' => u += 3,
+                b'y' => {
+                    let n = chars.next().and_then(char_value).ok_or_else(invalid)?;
+                    u += 4 + n;
+                }
+                _ => {
+                    let column = char_value(c).filter(|n| *n < 32).ok_or_else(invalid)?;
+                    for w in 0..5 {
+                        if column >> w & 1 == 1 {
+                            cells.push(Coord::new((5 * v + w) as i32, u as i32));
+                        }
+                    }
+                    u += 1;
+                }
+            }
+        }
+    }
+    if cells.is_empty() {
+        return Err(invalid());
+    }
+    Ok(to_rle(code, &cells))
+}
+
+fn char_value(c: u8) -> Option<usize> {
+    CHARS.iter().position(|x| *x == c)
+}
+
+fn wechsler(
+    cells: &HashSet<Coord>,
+    bounds: &Rect<i32>,
+    (a, b, c, d): (i32, i32, i32, i32),
+) -> String {
+    let (length, breadth) = if a != 0 {
+        (bounds.width, bounds.height)
+    } else {
+        (bounds.height, bounds.width)
+    };
+    let x0 = if a + b < 0 {
+        bounds.x + bounds.width - 1
+    } else {
+        bounds.x
+    };
+    let y0 = if c + d < 0 {
+        bounds.y + bounds.height - 1
+    } else {
+        bounds.y
+    };
+
+    let mut code = String::new();
+    for v in 0..(breadth + 4) / 5 {
+        if v != 0 {
+            code.push('z');
+        }
+        let mut zeroes = 0;
+        for u in 0..length {
+            let column = (0..5).fold(0, |column, w| {
+                let row = 5 * v + w;
+                let at = Coord::new(y0 + c * u + d * row, x0 + a * u + b * row);
+                column | (cells.contains(&at) as usize) << w
+            });
+            if column == 0 {
+                zeroes += 1;
+                continue;
+            }
+            while zeroes > 0 {
+                let run = ::std::cmp::min(zeroes, 39);
+                match run {
+                    1 => code.push('0'),
+                    2 => code.push('w'),
+                    3 => code.push('x'),
+                    _ => {
+                        code.push('y');
+                        code.push(CHARS[run - 4] as char);
+                    }
+                }
+                zeroes -= run;
+            }
+            code.push(CHARS[column] as char);
+        }
+    }
+    code
+}
+
+/// The bounding box of `cells` and the cells moved so that it starts at the
+/// origin, in a comparable form. `None` for an empty pattern.
+fn normalize(cells: &HashSet<Coord>) -> Option<(Rect<i32>, Vec<Coord>)> {
+    let min_row = cells.iter().map(|c| c.row).min()?;
+    let min_col = cells.iter().map(|c| c.col).min()?;
+    let max_row = cells.iter().map(|c| c.row).max()?;
+    let max_col = cells.iter().map(|c| c.col).max()?;
+    let mut shape: Vec<Coord> = cells
+        .iter()
+        .map(|c| Coord::new(c.row - min_row, c.col - min_col))
+        .collect();
+    shape.sort();
+    let bounds = Rect {
+        x: min_col,
+        y: min_row,
+        width: max_col - min_col + 1,
+        height: max_row - min_row + 1,
+    };
+    Some((bounds, shape))
+}
+
+/// One generation of `cells` on an unbounded plane.
+fn step(cells: &HashSet<Coord>, rule: Rule) -> HashSet<Coord> {
+    let mut neighbors: HashMap<Coord, u8> = HashMap::new();
+    for cell in cells {
+        for (r, c) in super::NEIGHBORHOOD.iter() {
+            *neighbors
+                .entry(Coord::new(cell.row + r, cell.col + c))
+                .or_insert(0) += 1;
+        }
+    }
+    let mut next: HashSet<Coord> = neighbors
+        .iter()
+        .filter(|(cell, n)| {
+            if cells.contains(cell) {
+                rule.survives(**n)
+            } else {
+                rule.born(**n)
+            }
+        })
+        .map(|(cell, _)| *cell)
+        .collect();
+    // Cells without any neighbor don't appear in the map.
+    if rule.survives(0) {
+        next.extend(cells.iter().filter(|c| !neighbors.contains_key(c)));
+    }
+    next
+}
+
+fn to_rle(name: &str, cells: &[Coord]) -> Rle {
+    let width = cells.iter().map(|c| c.col).max().unwrap() + 1;
+    let height = cells.iter().map(|c| c.row).max().unwrap() + 1;
+    let alive: HashSet<&Coord> = cells.iter().collect();
+
+    let mut content = Vec::new();
+    for row in 0..height {
+        if row != 0 {
+            content.push(RleTagSequence(1, RleTag::NextLine));
+        }
+        for col in 0..width {
+            let tag = if alive.contains(&Coord::new(row, col)) {
+                RleTag::Alive
+            } else {
+                RleTag::Dead
+            };
+            match content.last_mut() {
+                Some(RleTagSequence(n, last)) if *last == tag => *n += 1,
+                _ => content.push(RleTagSequence(1, tag)),
+            }
+        }
+    }
+
+    Rle {
+        comments: vec![RleComment::Name(name.to_string())],
+        size: RleFirstLine(width as usize, height as usize),
+        content,
+    }
+}
+
+impl World {
+    /// The apgcode of the live cells in `rect`, which must hold a single
+    /// still life, oscillator or spaceship, evolved under the world's rule
+    /// on an unbounded plane.
+    pub fn apgcode(&self, rect: &Rect<i32>) -> Result<String> {
+        let mut cells = Vec::new();
+        for row in rect.y..rect.y + rect.height {
+            for col in rect.x..rect.x + rect.width {
+                if self.cell((row, col)) == Cell::Alive {
+                    cells.push(Coord::new(row, col));
+                }
+            }
+        }
+        encode(&cells, self.rule)
+    }
+
+    /// Places the object named by `code` like `load_rle` does.
+    pub fn load_apgcode(&mut self, code: &str) -> Result<()> {
+        let rle = decode(code)?;
+        self.load_rle(rle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(rle: &str) -> Vec<Coord> {
+        let mut world = World::new(64, 64);
+        world.load_rle_str(rle).unwrap();
+        world.live_cells().collect()
+    }
+
+    #[test]
+    fn encode_test() {
+        let life = Rule::life();
+        let expectations = vec![
+            ("x = 2, y = 2\n2o$2o!", "xs4_33"),
+            ("x = 4, y = 3\nb2o$o2bo$b2o!", "xs6_696"),
+            ("x = 4, y = 4\nb2o$o2bo$bobo$2bo!", "xs7_2596"),
+            ("x = 3, y = 1\n3o!", "xp2_7"),
+            ("x = 3, y = 3\nbo$2bo$3o!", "xq4_153"),
+            ("x = 5, y = 4\nbo2bo$o$o3bo$4o!", "xq4_6frc"),
+        ];
+        for (rle, code) in expectations {
+            assert_eq!(encode(&cells(rle), life).unwrap(), code, "{}", rle);
+        }
+    }
+
+    #[test]
+    fn encode_errors_test() {
+        let life = Rule::life();
+        // A lone cell dies, the R-pentomino takes 1103 generations to settle.
+        assert_eq!(
+            encode(&cells("x = 1, y = 1\no!"), life),
+            Err(Error::NotPeriodic)
+        );
+        assert_eq!(
+            encode(&cells("x = 3, y = 3\nb2o$2o$bo!"), life),
+            Err(Error::NotPeriodic)
+        );
+        assert_eq!(encode(&[], life), Err(Error::NotPeriodic));
+    }
+
+    #[test]
+    fn decode_test() {
+        let rle = decode("xq4_153").unwrap();
+        assert_eq!(rle.size, RleFirstLine(3, 3));
+        let mut world = World::new(16, 16);
+        world.load_rle(rle).unwrap();
+        // Phase and orientation are the canonical ones, not the usual glider.
+        let glider: Vec<_> = world.live_cells().collect();
+        assert_eq!(
+            glider,
+            vec![(7, 7), (7, 8), (7, 9), (8, 9), (9, 8)]
+                .into_iter()
+                .map(Coord::from)
+                .collect::<Vec<_>>()
+        );
+
+        // Blank columns and several strips.
+        assert_eq!(decode("3x3").unwrap().size, RleFirstLine(5, 2));
+        assert_eq!(decode("3y13").unwrap().size, RleFirstLine(7, 2));
+        assert_eq!(decode("3zz3").unwrap().size, RleFirstLine(1, 12));
+
+        assert!(decode("xs4_3!").is_err());
+        assert!(decode("xr4_33").is_err());
+        assert!(decode("xs_33").is_err());
+        assert!(decode("xs4_").is_err());
+        assert!(decode("y").is_err());
+        // Prefixes are checked without slicing through multibyte characters.
+        assert!(decode("aé_1").is_err());
+        assert!(decode("é_1").is_err());
+        assert!(decode("xé4_33").is_err());
+        assert!(decode("x_33").is_err());
+    }
+
+    #[test]
+    fn round_trip_test() {
+        for code in &[
+            "xs4_33",
+            "xs8_6996",
+            "xp2_7",
+            "xp15_4r4z4r4",
+            "xp3_co9nas0san9oczgoldlo0oldlogz1047210127401",
+            "xq4_153",
+        ] {
+            let mut world = World::new(64, 64);
+            world.load_apgcode(code).unwrap();
+            let bounds = world.bounding_box().unwrap();
+            let rect = Rect {
+                x: bounds.x as i32,
+                y: bounds.y as i32,
+                width: bounds.width as i32,
+                height: bounds.height as i32,
+            };
+            assert_eq!(world.apgcode(&rect).unwrap(), *code);
+        }
+    }
+}
@@ -0,0 +1,323 @@
+//! Automata with more than two states.
+//!
+//! A world running one of these keeps the state of every cell next to its
+//! `cells`, which then tell the cells in a state other than 0 apart: the
+//! renderer, bounding boxes and population keep working unchanged.
+
+use std::str::FromStr;
+
+use super::block::BlockRule;
+use super::parser::{multi_state_name, RleTag};
+use super::table::{Neighborhood, RuleTable};
+use super::{wireworld, Cell, Coord, Error, Result, World, NEIGHBORHOOD};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Automaton {
+    /// See `wireworld::Wire` for its states.
+    WireWorld,
+    Table(RuleTable),
+    /// A two-state block automaton, stepped by `step_block_rule`.
+    Block(BlockRule),
+}
+
+impl Automaton {
+    pub fn name(&self) -> &str {
+        match self {
+            Automaton::WireWorld => "WireWorld",
+            Automaton::Table(table) => table.name(),
+            Automaton::Block(rule) => rule.name(),
+        }
+    }
+
+    /// Number of states, state 0 being the background.
+    pub fn states(&self) -> usize {
+        match self {
+            Automaton::WireWorld => wireworld::STATES,
+            Automaton::Table(table) => table.states(),
+            Automaton::Block(_) => 2,
+        }
+    }
+
+    /// The `0xRRGGBB` color the automaton gives to `state`, if any.
+    pub fn color(&self, state: u8) -> Option<u32> {
+        match self {
+            Automaton::WireWorld => wireworld::color(state),
+            Automaton::Table(table) => table.color(state),
+            Automaton::Block(_) => None,
+        }
+    }
+
+    fn neighborhood(&self) -> Neighborhood {
+        match self {
+            Automaton::WireWorld => Neighborhood::Moore,
+            Automaton::Table(table) => table.neighborhood(),
+            Automaton::Block(_) => unreachable!("Block rules have no neighborhood"),
+        }
+    }
+
+    fn next_state(&mut self, center: u8, neighbors: &[u8]) -> u8 {
+        match self {
+            Automaton::WireWorld => wireworld::next_state(center, neighbors),
+            Automaton::Table(table) => table.next_state(center, neighbors),
+            Automaton::Block(_) => unreachable!("Block rules step whole blocks"),
+        }
+    }
+}
+
+/// Parses the name of a built-in automaton, ignoring case, or a block rule
+/// in MCell notation.
+impl FromStr for Automaton {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Automaton> {
+        match name.to_lowercase().as_str() {
+            "wireworld" => Ok(Automaton::WireWorld),
+            "bbm" => Ok(Automaton::Block(BlockRule::billiard_ball_machine())),
+            "critters" => Ok(Automaton::Block(BlockRule::critters())),
+            _ if name.starts_with("MS,D") => Ok(Automaton::Block(name.parse()?)),
+            _ => Err(Error::UnknownAutomaton(name.to_string())),
+        }
+    }
+}
+
+impl World {
+    /// Runs `automaton` instead of the rule, or the rule again for `None`.
+    /// Cells keep their state, alive cells being in state 1.
+    pub fn set_automaton(&mut self, automaton: Option<Automaton>) {
+        self.states = match automaton {
+            Some(ref automaton) => self
+                .states()
+                .map(|state| state.min(automaton.states() as u8 - 1))
+                .collect(),
+            None => Vec::new(),
+        };
+        for (cell, state) in self.cells.iter_mut().zip(self.states.iter()) {
+            *cell = if *state == 0 { Cell::Dead } else { Cell::Alive };
+        }
+        self.state_cache = self.states.clone();
+        self.automaton = automaton;
+        self.mark_all_changed();
+    }
+
+    pub fn automaton(&self) -> Option<&Automaton> {
+        self.automaton.as_ref()
+    }
+
+    /// Parses a Golly `.rule` file and runs its table.
+    pub fn load_rule_table(&mut self, table: &str) -> Result<()> {
+        self.set_automaton(Some(Automaton::Table(table.parse()?)));
+        Ok(())
+    }
+
+    /// The state of the cell at `at`: 0 or 1 under a two-state rule.
+    pub fn state(&self, at: impl Into<Coord>) -> u8 {
+        let at = at.into();
+        self.state_at(self.get_index(at.row, at.col) as usize)
+    }
+
+    /// The state of every cell, row by row.
+    pub fn states(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..self.cells.len()).map(move |idx| self.state_at(idx))
+    }
+
+    /// Sets the state of the cell at `at`, and of its images under the
+    /// drawing symmetry. States the automaton doesn't have are ignored.
+    pub fn set_state(&mut self, at: impl Into<Coord>, state: u8) {
+        if state as usize >= self.state_count() {
+            return;
+        }
+        for idx in self.images(at.into()) {
+            self.put_state(idx, state);
+        }
+    }
+
+    /// The rule or automaton, as named in RLE headers.
+    pub(crate) fn rule_name(&self) -> String {
+        match self.automaton {
+            Some(ref automaton) => automaton.name().to_string(),
+            None => self.rule.to_string(),
+        }
+    }
+
+    pub(crate) fn state_count(&self) -> usize {
+        self.automaton.as_ref().map_or(2, Automaton::states)
+    }
+
+    /// The state an RLE cell is loaded as. Multi-state cells are only known
+    /// to automata with more than two states, up to their last state.
+    pub(crate) fn tag_state(&self, tag: RleTag) -> Result<u8> {
+        match tag {
+            RleTag::Alive => Ok(1),
+            RleTag::State(state)
+                if self.state_count() > 2 && (state as usize) < self.state_count() =>
+            {
+                Ok(state)
+            }
+            RleTag::State(state) => Err(Error::Parse(format!(
+                "unknown cell {:?} under {}",
+                multi_state_name(state),
+                self.rule_name()
+            ))),
+            RleTag::Dead | RleTag::NextLine => Ok(0),
+        }
+    }
+
+    pub(crate) fn state_at(&self, idx: usize) -> u8 {
+        match self.states.get(idx) {
+            Some(&state) => state,
+            None => self.cells[idx] as u8,
+        }
+    }
+
+    pub(crate) fn put_state(&mut self, idx: i32, state: u8) {
+        let cell = if state == 0 { Cell::Dead } else { Cell::Alive };
+        self.put(idx, cell);
+        if let Some(s) = self.states.get_mut(idx as usize) {
+            *s = state;
+        }
+    }
+
+    /// `next_tick` under an automaton: like the two-state path, only the
+    /// cells around the ones that changed are looked at.
+    pub(crate) fn step_automaton(&mut self) {
+        let mut automaton = self.automaton.take().expect("An automaton is running");
+        let offsets = automaton.neighborhood().offsets();
+
+        let mut to_check = std::mem::take(&mut self.to_check);
+        to_check.clear();
+        for &idx in self.active.iter() {
+            let (row, col) = self.to_coords(idx);
+            to_check.insert(idx);
+            for (r, c) in NEIGHBORHOOD.iter() {
+                if let Some(neighbor) = self.neighbor_index(row + r, col + c) {
+                    to_check.insert(neighbor);
+                }
+            }
+        }
+
+        self.changed_cells.clear();
+        let mut neighbors = [0; 8];
+        for &idx in to_check.iter() {
+            let (row, col) = self.to_coords(idx);
+            for (neighbor, (r, c)) in neighbors.iter_mut().zip(offsets.iter()) {
+                *neighbor = self
+                    .neighbor_index(row + r, col + c)
+                    .map_or(0, |i| self.states[i as usize]);
+            }
+            let state = self.states[idx as usize];
+            let next = automaton.next_state(state, &neighbors[..offsets.len()]);
+            if next != state {
+                self.changed_cells.push(idx);
+            }
+            self.state_cache[idx as usize] = next;
+        }
+        self.to_check = to_check;
+        self.automaton = Some(automaton);
+
+        ::std::mem::swap(&mut self.states, &mut self.state_cache);
+        for &idx in self.changed_cells.iter() {
+            let idx = idx as usize;
+            self.cells[idx] = if self.states[idx] == 0 {
+                Cell::Dead
+            } else {
+                Cell::Alive
+            };
+        }
+        self.end_tick();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Topology;
+
+    const WIREWORLD: &str = "@RULE WireWorld
+@TABLE
+n_states:4
+neighborhood:Moore
+symmetries:permute
+var a={0,1,2,3}
+var b={0,1,2,3}
+var c={0,1,2,3}
+var d={0,1,2,3}
+var e={0,1,2,3}
+var f={0,1,2,3}
+var g={0,2,3}
+var h={0,2,3}
+var i={0,2,3}
+var j={0,2,3}
+var k={0,2,3}
+var l={0,2,3}
+var m={0,2,3}
+1,a,b,c,d,e,f,g,h,2
+2,a,b,c,d,e,f,g,h,3
+3,1,g,h,i,j,k,l,m,1
+3,1,1,g,h,i,j,k,l,1
+@COLORS
+1 0 128 255
+";
+
+    /// A wire around the torus, with a signal going right.
+    fn wire_loop() -> World {
+        let mut world = World::new(8, 3);
+        world.load_rule_table(WIREWORLD).unwrap();
+        for col in 0..8 {
+            world.set_state((1, col), 3);
+        }
+        world.set_state((1, 2), 1);
+        world.set_state((1, 1), 2);
+        world
+    }
+
+    #[test]
+    fn table_stepping_test() {
+        let mut world = wire_loop();
+        let start: Vec<u8> = world.states().collect();
+        assert_eq!(world.population(), 8);
+
+        world.next_tick();
+        assert_eq!(world.state((1, 3)), 1);
+        assert_eq!(world.state((1, 2)), 2);
+        assert_eq!(world.state((1, 1)), 3);
+        let mut changed = world.changed_cells().to_vec();
+        changed.sort_unstable();
+        assert_eq!(changed, vec![9, 10, 11]);
+
+        for _ in 1..8 {
+            world.next_tick();
+        }
+        assert_eq!(world.states().collect::<Vec<_>>(), start);
+        assert_eq!(world.population(), 8);
+    }
+
+    #[test]
+    fn edits_and_switching_test() {
+        let mut world = wire_loop();
+        assert_eq!(world.automaton().map(Automaton::name), Some("WireWorld"));
+        world.set_state((0, 0), 7);
+        assert_eq!(world.state((0, 0)), 0);
+        world.set_cell((0, 0), Cell::Alive);
+        assert_eq!(world.state((0, 0)), 1);
+
+        // Back to Life, wires become alive cells.
+        world.set_automaton(None);
+        assert_eq!(world.state((1, 1)), 1);
+        assert_eq!(world.population(), 9);
+        world.next_tick();
+        assert!(world.states().all(|s| s <= 1));
+    }
+
+    #[test]
+    fn plane_edges_test() {
+        // A head at the edge of a plane sees nothing past it.
+        let mut world = World::new(3, 3);
+        world.set_topology(Topology::Plane);
+        world.load_rule_table(WIREWORLD).unwrap();
+        world.set_state((0, 0), 3);
+        world.set_state((2, 2), 1);
+        world.next_tick();
+        assert_eq!(world.state((0, 0)), 3);
+        assert_eq!(world.state((2, 2)), 2);
+    }
+}
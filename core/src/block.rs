@@ -0,0 +1,312 @@
+//! Block cellular automata, with the Margolus neighborhood.
+//!
+//! The board is cut into 2×2 blocks, and every block is replaced according
+//! to a table of 16 entries. The partition moves by one cell diagonally from
+//! one generation to the next: blocks start on even rows and columns at even
+//! generations, and on odd ones at odd generations.
+//!
+//! A block is numbered by its alive cells: 1 for the top-left one, 2 for the
+//! top-right one, 4 for the bottom-left one and 8 for the bottom-right one,
+//! as in MCell's `MS,D` notation.
+
+use std::fmt;
+use std::str::FromStr;
+
+use super::{Automaton, Cell, Error, Result, Topology, World};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockRule {
+    name: String,
+    table: [u8; 16],
+}
+
+impl BlockRule {
+    /// Builds a rule from the block each of the 16 blocks turns into.
+    pub fn new(table: [u8; 16]) -> Result<BlockRule> {
+        if let Some(block) = table.iter().find(|&&block| block >= 16) {
+            return Err(Error::InvalidRule(format!("no block {}", block)));
+        }
+        let name = format!("MS,D{}", join(&table));
+        Ok(BlockRule { name, table })
+    }
+
+    /// The Billiard Ball Machine, whose balls bounce off each other and off
+    /// walls.
+    pub fn billiard_ball_machine() -> BlockRule {
+        BlockRule {
+            name: "BBM".to_string(),
+            table: [0, 8, 4, 3, 2, 5, 9, 7, 1, 6, 10, 11, 12, 13, 14, 15],
+        }
+    }
+
+    /// Critters, which complements the blocks that don't have two alive
+    /// cells, and turns those that had three by half a turn.
+    pub fn critters() -> BlockRule {
+        BlockRule {
+            name: "Critters".to_string(),
+            table: [15, 14, 13, 3, 11, 5, 6, 1, 7, 9, 10, 2, 12, 4, 8, 0],
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn table(&self) -> &[u8; 16] {
+        &self.table
+    }
+
+    /// Whether every block has a single block turning into it, so that the
+    /// rule can run backwards.
+    pub fn is_reversible(&self) -> bool {
+        self.inverse().is_some()
+    }
+
+    fn inverse(&self) -> Option<[u8; 16]> {
+        let mut inverse = [16; 16];
+        for (block, &next) in self.table.iter().enumerate() {
+            if inverse[next as usize] != 16 {
+                return None;
+            }
+            inverse[next as usize] = block as u8;
+        }
+        Some(inverse)
+    }
+}
+
+fn join(table: &[u8; 16]) -> String {
+    table
+        .iter()
+        .map(u8::to_string)
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+impl fmt::Display for BlockRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MS,D{}", join(&self.table))
+    }
+}
+
+/// Parses the MCell notation, `MS,D` followed by the 16 blocks separated by
+/// semicolons, or the 16 blocks alone.
+impl FromStr for BlockRule {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<BlockRule> {
+        let invalid = || Error::InvalidRule(s.to_string());
+        let blocks = s.trim();
+        let blocks = blocks.strip_prefix("MS,D").unwrap_or(blocks);
+        let blocks = blocks
+            .split(&[';', ','][..])
+            .map(|block| block.trim().parse::<u8>().map_err(|_| invalid()))
+            .collect::<Result<Vec<_>>>()?;
+        if blocks.len() != 16 {
+            return Err(invalid());
+        }
+        let mut table = [0; 16];
+        table.copy_from_slice(&blocks);
+        BlockRule::new(table)
+    }
+}
+
+impl World {
+    /// Undoes the last generation of a reversible block rule, on a torus of
+    /// even width and height: elsewhere blocks cut by the edges lose the
+    /// cells past them, and can't be undone.
+    pub fn previous_tick(&mut self) -> Result<()> {
+        let inverse = match self.automaton {
+            Some(Automaton::Block(ref rule)) => rule.inverse().ok_or_else(|| {
+                Error::NotReversible(format!("{} is not reversible", rule.name()))
+            })?,
+            _ => return Err(Error::NotReversible("not a block rule".to_string())),
+        };
+        if self.topology == Topology::Plane {
+            return Err(Error::NotReversible(
+                "blocks are cut by the edges of a plane".to_string(),
+            ));
+        }
+        if self.width % 2 != 0 || self.height % 2 != 0 {
+            return Err(Error::NotReversible(format!(
+                "blocks are cut by the edges of a {}x{} board",
+                self.width, self.height
+            )));
+        }
+        if self.generations == 0 {
+            return Err(Error::NotReversible("at generation 0".to_string()));
+        }
+        self.step_blocks(&inverse, (self.generations - 1) % 2);
+        self.generations -= 1;
+        self.note_changes();
+        Ok(())
+    }
+
+    /// `next_tick` under a block rule.
+    pub(crate) fn step_block_rule(&mut self) {
+        let table = match self.automaton {
+            Some(Automaton::Block(ref rule)) => rule.table,
+            _ => unreachable!("A block rule is running"),
+        };
+        self.step_blocks(&table, self.generations % 2);
+        self.end_tick();
+    }
+
+    /// Replaces the blocks starting `offset` cells from the top-left corner,
+    /// in place since blocks don't overlap.
+    fn step_blocks(&mut self, table: &[u8; 16], offset: u32) {
+        self.changed_cells.clear();
+        let offset = offset as i32;
+        for row in (offset..self.height).step_by(2) {
+            for col in (offset..self.width).step_by(2) {
+                let cells = [
+                    self.block_index(row, col),
+                    self.block_index(row, col + 1),
+                    self.block_index(row + 1, col),
+                    self.block_index(row + 1, col + 1),
+                ];
+                let block = cells
+                    .iter()
+                    .enumerate()
+                    .fold(0, |block, (bit, idx)| match idx {
+                        Some(idx) if self.cells[*idx as usize] == Cell::Alive => block | 1 << bit,
+                        _ => block,
+                    });
+                let next = table[block];
+                for (bit, idx) in cells.iter().enumerate() {
+                    let idx = match idx {
+                        Some(idx) => *idx,
+                        None => continue,
+                    };
+                    let cell = if next & 1 << bit == 0 {
+                        Cell::Dead
+                    } else {
+                        Cell::Alive
+                    };
+                    if self.cells[idx as usize] != cell {
+                        self.cells[idx as usize] = cell;
+                        if let Some(state) = self.states.get_mut(idx as usize) {
+                            *state = cell as u8;
+                        }
+                        self.changed_cells.push(idx);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Index of a cell of a block. Blocks wrap around the edges of a torus
+    /// when they have an even length; otherwise, cells of blocks cut by an
+    /// edge are dead past it.
+    fn block_index(&self, row: i32, col: i32) -> Option<i32> {
+        let wrap = |n: i32, len: i32| match n {
+            n if n < len => Some(n),
+            _ if self.topology == Topology::Torus && len % 2 == 0 => Some(0),
+            _ => None,
+        };
+        Some(wrap(row, self.height)? * self.width + wrap(col, self.width)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block_world(width: i32, height: i32, rule: BlockRule) -> World {
+        let mut world = World::new(width, height);
+        world.set_automaton(Some(Automaton::Block(rule)));
+        world
+    }
+
+    #[test]
+    fn parse_test() {
+        let bbm = BlockRule::billiard_ball_machine();
+        let parsed: BlockRule = bbm.to_string().parse().unwrap();
+        assert_eq!(parsed.table(), bbm.table());
+        assert_eq!(parsed.name(), "MS,D0;8;4;3;2;5;9;7;1;6;10;11;12;13;14;15");
+        let parsed: BlockRule = "15,14,13,3,11,5,6,1,7,9,10,2,12,4,8,0".parse().unwrap();
+        assert_eq!(parsed.table(), BlockRule::critters().table());
+
+        assert!("MS,D0;1;2".parse::<BlockRule>().is_err());
+        assert!("MS,D0;1;2;3;4;5;6;7;8;9;10;11;12;13;14;16"
+            .parse::<BlockRule>()
+            .is_err());
+        assert!("MS,D0;1;2;3;4;5;6;7;8;9;10;11;12;13;14;x"
+            .parse::<BlockRule>()
+            .is_err());
+        assert_eq!(
+            "critters".parse::<Automaton>(),
+            Ok(Automaton::Block(BlockRule::critters()))
+        );
+    }
+
+    #[test]
+    fn ball_test() {
+        // A lone ball moves diagonally, one cell per generation, whatever
+        // the partition.
+        let mut world = block_world(8, 8, BlockRule::billiard_ball_machine());
+        world.set_cell((2, 2), Cell::Alive);
+        for generation in 1..=3 {
+            world.next_tick();
+            assert_eq!(world.cell((2 + generation, 2 + generation)), Cell::Alive);
+            assert_eq!(world.population(), 1);
+        }
+        assert_eq!(world.changed_cells().len(), 2);
+
+        // And wraps around the torus.
+        for _ in 0..3 {
+            world.next_tick();
+        }
+        assert_eq!(world.cell((0, 0)), Cell::Alive);
+    }
+
+    #[test]
+    fn backwards_test() {
+        let mut world = block_world(32, 24, BlockRule::critters());
+        world.randomize(0.3, 5);
+        let start = world.cells().to_vec();
+        for _ in 0..25 {
+            world.next_tick();
+        }
+        assert_ne!(world.cells(), &start[..]);
+        for _ in 0..25 {
+            world.previous_tick().unwrap();
+        }
+        assert_eq!(world.cells(), &start[..]);
+        assert_eq!(world.generations(), 0);
+        assert!(world.previous_tick().is_err());
+
+        let rule = BlockRule::new([0; 16]).unwrap();
+        assert!(!rule.is_reversible());
+        let mut world = block_world(8, 8, rule);
+        world.next_tick();
+        assert!(world.previous_tick().is_err());
+        world.set_automaton(None);
+        assert!(world.previous_tick().is_err());
+    }
+
+    #[test]
+    fn odd_sizes_test() {
+        // Blocks cut by an edge see dead cells past it, and never write
+        // twice to a cell.
+        for &topology in &[Topology::Torus, Topology::Plane] {
+            let mut world = block_world(5, 3, BlockRule::billiard_ball_machine());
+            world.set_topology(topology);
+            world.set_cell((2, 4), Cell::Alive);
+            world.next_tick();
+            assert_eq!(world.population(), 0);
+            let mut changed = world.changed_cells().to_vec();
+            changed.dedup();
+            assert_eq!(changed, vec![14]);
+        }
+
+        // Running backwards would lose the cells past the edges.
+        let mut world = block_world(7, 7, BlockRule::critters());
+        world.randomize(0.3, 5);
+        world.next_tick();
+        assert!(world.previous_tick().is_err());
+        let mut world = block_world(8, 8, BlockRule::critters());
+        world.set_topology(Topology::Plane);
+        world.next_tick();
+        assert!(world.previous_tick().is_err());
+    }
+}
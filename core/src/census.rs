@@ -0,0 +1,288 @@
+//! Soup census: running random soups until they settle and counting the
+//! objects they leave, like apgsearch does.
+//!
+//! Soups are squares of `SOUP_SIZE` cells, each alive with probability one
+//! half, run on a plane with room around them until `run_until_stable` finds
+//! them settled, counting the objects that reached its edges, spaceships
+//! flying off or anything else, as they got there. The cells left are
+//! then split into objects over a period: cells alive at most two cells away
+//! from each other, in the same generation or one after the other, belong to
+//! the same object, as they can make or keep a cell alive between them.
+//! Objects are named by their apgcode.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write;
+
+use super::{apgcode, Coord, Rect, Rule, Symmetry, Topology, World};
+
+pub const SOUP_SIZE: i32 = 16;
+/// Name counted for objects that don't come back to their shape on their
+/// own.
+pub const UNIDENTIFIED: &str = "zz_unidentified";
+
+/// Room around soups and their symmetric copies.
+const MARGIN: i32 = 112;
+
+/// Object counts over many soups.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Census {
+    rule: Rule,
+    symmetry: Symmetry,
+    soups: u64,
+    unsettled: u64,
+    counts: HashMap<String, u64>,
+}
+
+impl Census {
+    pub fn new(rule: Rule, symmetry: Symmetry) -> Census {
+        Census {
+            rule,
+            symmetry,
+            soups: 0,
+            unsettled: 0,
+            counts: HashMap::new(),
+        }
+    }
+
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    pub fn symmetry(&self) -> Symmetry {
+        self.symmetry
+    }
+
+    /// Soups run so far, settled or not.
+    pub fn soups(&self) -> u64 {
+        self.soups
+    }
+
+    /// Soups that didn't settle in time, whose objects aren't counted.
+    pub fn unsettled(&self) -> u64 {
+        self.unsettled
+    }
+
+    pub fn count(&self, code: &str) -> u64 {
+        self.counts.get(code).copied().unwrap_or(0)
+    }
+
+    /// Every object seen with its count, the most common first.
+    pub fn objects(&self) -> Vec<(&str, u64)> {
+        let mut objects: Vec<(&str, u64)> = self
+            .counts
+            .iter()
+            .map(|(code, &count)| (code.as_str(), count))
+            .collect();
+        objects.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        objects
+    }
+
+    /// Runs the soup drawn from `seed` for at most `max_generations`, and
+    /// counts its objects if it settled.
+    pub fn add_soup(&mut self, seed: u32, max_generations: u32) {
+        let size = 2 * (SOUP_SIZE + MARGIN);
+        let mut world = World::new(size, size);
+        world.set_rule(self.rule);
+        world.set_topology(Topology::Plane);
+        let rect = Rect {
+            x: MARGIN,
+            y: MARGIN,
+            width: SOUP_SIZE,
+            height: SOUP_SIZE,
+        };
+        world.random_soup(&rect, 0.5, self.symmetry, seed);
+
+        self.soups += 1;
+        match settled_objects(world, max_generations) {
+            Some(objects) => {
+                for code in objects {
+                    *self.counts.entry(code).or_insert(0) += 1;
+                }
+            }
+            None => self.unsettled += 1,
+        }
+    }
+
+    /// Adds the soups of another census, run under the same rule and
+    /// symmetry.
+    pub fn merge(&mut self, other: &Census) {
+        self.soups += other.soups;
+        self.unsettled += other.unsettled;
+        for (code, count) in &other.counts {
+            *self.counts.entry(code.clone()).or_insert(0) += count;
+        }
+    }
+
+    /// A header in `#` comments, then a line per object with its apgcode and
+    /// count separated by a tab.
+    pub fn report(&self) -> String {
+        let mut report = String::new();
+        writeln!(
+            report,
+            "# {} soups of {}x{} cells, {} under {}",
+            self.soups, SOUP_SIZE, SOUP_SIZE, self.symmetry, self.rule
+        )
+        .unwrap();
+        if self.unsettled > 0 {
+            writeln!(report, "# {} of them didn't settle", self.unsettled).unwrap();
+        }
+        for (code, count) in self.objects() {
+            writeln!(report, "{}\t{}", code, count).unwrap();
+        }
+        report
+    }
+}
+
+/// Runs `world` until it settles, and names the objects that escaped or
+/// were left. `None` if it doesn't settle within `max_generations`.
+fn settled_objects(mut world: World, max_generations: u32) -> Option<Vec<String>> {
+    let stable = world.run_until_stable(max_generations)?;
+    let mut objects: Vec<String> = stable
+        .escaped
+        .iter()
+        .map(|cells| name(cells, world.rule))
+        .collect();
+    objects.extend(split_objects(&mut world, stable.period));
+    Some(objects)
+}
+
+/// Splits the cells of a board repeating every `period` generations into
+/// objects, and names them.
+fn split_objects(world: &mut World, period: u32) -> Vec<String> {
+    let mut phases: Vec<HashSet<Coord>> = Vec::new();
+    for _ in 0..period {
+        phases.push(world.live_cells().collect());
+        world.next_tick();
+    }
+
+    let mut parents: HashMap<Coord, Coord> = HashMap::new();
+    for (phase, cells) in phases.iter().enumerate() {
+        let previous = &phases[(phase + phases.len() - 1) % phases.len()];
+        for &cell in cells {
+            for r in -2..=2 {
+                for c in -2..=2 {
+                    let near = Coord::new(cell.row + r, cell.col + c);
+                    if cells.contains(&near) || previous.contains(&near) {
+                        union(&mut parents, cell, near);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut objects: BTreeMap<Coord, Vec<Coord>> = BTreeMap::new();
+    for &cell in &phases[0] {
+        let root = find(&mut parents, cell);
+        objects.entry(root).or_default().push(cell);
+    }
+    objects
+        .values()
+        .map(|cells| name(cells, world.rule))
+        .collect()
+}
+
+fn find(parents: &mut HashMap<Coord, Coord>, cell: Coord) -> Coord {
+    let parent = *parents.entry(cell).or_insert(cell);
+    if parent == cell {
+        return cell;
+    }
+    let root = find(parents, parent);
+    parents.insert(cell, root);
+    root
+}
+
+fn union(parents: &mut HashMap<Coord, Coord>, a: Coord, b: Coord) {
+    let (a, b) = (find(parents, a), find(parents, b));
+    if a != b {
+        parents.insert(a.max(b), a.min(b));
+    }
+}
+
+fn name(cells: &[Coord], rule: Rule) -> String {
+    apgcode::encode(cells, rule).unwrap_or_else(|_| UNIDENTIFIED.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Cell;
+
+    fn board(rle: &str) -> World {
+        let mut world = World::new(64, 64);
+        world.set_topology(Topology::Plane);
+        world.load_rle_str(rle).unwrap();
+        world
+    }
+
+    fn sorted(mut names: Vec<String>) -> Vec<String> {
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn objects_test() {
+        // A block, a blinker and a glider flying off.
+        let world = board("x = 13, y = 8\n2o3b3o$2o4$11bo$12bo$10b3o!");
+        assert_eq!(
+            sorted(settled_objects(world, 1000).unwrap()),
+            vec!["xp2_7", "xq4_153", "xs4_33"]
+        );
+
+        // The pulsar has phases made of several pieces.
+        let pulsar = "x = 13, y = 13
+2b3o3b3o2b2$o4bobo4bo$o4bobo4bo$o4bobo4bo$2b3o3b3o2b2$2b3o3b3o2b$o4bob
+o4bo$o4bobo4bo$o4bobo4bo2$2b3o3b3o!";
+        let objects = settled_objects(board(pulsar), 1000).unwrap();
+        assert_eq!(objects.len(), 1);
+        assert!(objects[0].starts_with("xp3_"), "{}", objects[0]);
+
+        // The R-pentomino takes 1103 generations to settle.
+        let r_pentomino = board("x = 3, y = 3\nb2o$2o$bo!");
+        assert_eq!(settled_objects(r_pentomino, 100), None);
+    }
+
+    #[test]
+    fn edge_objects_test() {
+        // Whatever changes near the edges is counted as it gets there: a
+        // blinker, and an R-pentomino that doesn't settle in time to be
+        // named. The block in the middle stays.
+        let mut world = board("x = 2, y = 2\n2o$2o!");
+        for col in 30..33 {
+            world.set_cell((1, col), Cell::Alive);
+        }
+        for &at in &[(60, 11), (60, 12), (61, 10), (61, 11), (62, 11)] {
+            world.set_cell(at, Cell::Alive);
+        }
+        assert_eq!(
+            sorted(settled_objects(world, 1000).unwrap()),
+            vec!["xp2_7", "xs4_33", UNIDENTIFIED]
+        );
+    }
+
+    #[test]
+    fn census_test() {
+        let mut census = Census::new(Rule::life(), Symmetry::C1);
+        for seed in 0..4 {
+            census.add_soup(seed, 10_000);
+        }
+        assert_eq!(census.soups(), 4);
+        assert!(census.count("xs4_33") > 0);
+        let objects = census.objects();
+        assert!(objects.windows(2).all(|w| w[0].1 >= w[1].1));
+
+        // Counts add up the same in two halves.
+        let mut halves = Census::new(Rule::life(), Symmetry::C1);
+        for range in &[0..2, 2..4] {
+            let mut half = Census::new(Rule::life(), Symmetry::C1);
+            for seed in range.clone() {
+                half.add_soup(seed, 10_000);
+            }
+            halves.merge(&half);
+        }
+        assert_eq!(halves, census);
+
+        let report = census.report();
+        assert!(report.starts_with("# 4 soups of 16x16 cells, C1 under B3/S23\n"));
+        assert!(report.contains(&format!("\nxs4_33\t{}\n", census.count("xs4_33"))));
+    }
+}
@@ -0,0 +1,503 @@
+//! Drawing tools.
+//!
+//! Shapes are drawn with `set_cell`, so every touched cell is recorded in
+//! `changed_cells` and mirrored according to the drawing symmetry. On a torus
+//! they wrap around the edges; on a plane the parts past the edges are
+//! clipped.
+
+use std::str::FromStr;
+
+use super::active_set::ActiveSet;
+use super::parser::{parse_rle, RleTag, RleTagSequence};
+use super::{Coord, Error, Rect, Result, Topology, World, NEIGHBORHOOD};
+
+/// Symmetry applied to every edit, around the center set with
+/// `World::set_draw_symmetry`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DrawSymmetry {
+    #[default]
+    None,
+    /// Mirrored left to right.
+    Horizontal,
+    /// Mirrored top to bottom.
+    Vertical,
+    /// Mirrored left to right and top to bottom.
+    Both,
+    /// Mirrored across the diagonal going down to the right.
+    Diagonal,
+    /// Repeated every quarter turn.
+    Rotate90,
+    /// Repeated every half turn.
+    Rotate180,
+}
+
+impl FromStr for DrawSymmetry {
+    type Err = Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(DrawSymmetry::None),
+            "horizontal" => Ok(DrawSymmetry::Horizontal),
+            "vertical" => Ok(DrawSymmetry::Vertical),
+            "both" => Ok(DrawSymmetry::Both),
+            "diagonal" => Ok(DrawSymmetry::Diagonal),
+            "90" => Ok(DrawSymmetry::Rotate90),
+            "180" => Ok(DrawSymmetry::Rotate180),
+            _ => Err(Error::UnknownSymmetry(s.to_string())),
+        }
+    }
+}
+
+impl DrawSymmetry {
+    /// Where the symmetry sends an offset from the center, for every image
+    /// but the offset itself.
+    pub(crate) fn images(self, (dr, dc): (i32, i32)) -> Vec<(i32, i32)> {
+        match self {
+            DrawSymmetry::None => vec![],
+            DrawSymmetry::Horizontal => vec![(dr, -dc)],
+            DrawSymmetry::Vertical => vec![(-dr, dc)],
+            DrawSymmetry::Both => vec![(dr, -dc), (-dr, dc), (-dr, -dc)],
+            DrawSymmetry::Diagonal => vec![(dc, dr)],
+            DrawSymmetry::Rotate90 => vec![(dc, -dr), (-dr, -dc), (-dc, dr)],
+            DrawSymmetry::Rotate180 => vec![(-dr, -dc)],
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fill {
+    Outline,
+    Solid,
+}
+
+/// Which cells count as adjacent for `flood_fill`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Connectivity {
+    /// Orthogonal neighbors only.
+    Four,
+    /// Orthogonal and diagonal neighbors.
+    Eight,
+}
+
+impl FromStr for Connectivity {
+    type Err = Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "4" => Ok(Connectivity::Four),
+            "8" => Ok(Connectivity::Eight),
+            _ => Err(Error::UnknownConnectivity(s.to_string())),
+        }
+    }
+}
+
+impl Connectivity {
+    fn offsets(self) -> &'static [(i32, i32)] {
+        match self {
+            Connectivity::Four => &[(-1, 0), (0, -1), (0, 1), (1, 0)],
+            Connectivity::Eight => &NEIGHBORHOOD,
+        }
+    }
+}
+
+impl World {
+    /// Makes `set_cell`, `toggle`, the shapes and `paste_rle` mirror every
+    /// edit. The center is given in half cells: `(2 * row, 2 * col)` is the
+    /// center of the cell at `(row, col)`, and odd coordinates fall on the
+    /// edges between cells. Quarter turns need both coordinates to be even
+    /// or both odd.
+    pub fn set_draw_symmetry(&mut self, symmetry: DrawSymmetry, center: impl Into<Coord>) {
+        self.draw_symmetry = symmetry;
+        self.symmetry_center = center.into();
+    }
+
+    pub fn draw_symmetry(&self) -> (DrawSymmetry, Coord) {
+        (self.draw_symmetry, self.symmetry_center)
+    }
+
+    /// Indexes of the cell at `at` and of its images under the drawing
+    /// symmetry, without duplicates. Images past the edges wrap around on a
+    /// torus and are dropped on a plane.
+    pub(crate) fn images(&self, at: Coord) -> Vec<i32> {
+        let idx = self.get_index(at.row, at.col);
+        let mut images = vec![idx];
+        let (row, col) = self.to_coords(idx);
+        let center = self.symmetry_center;
+        let offset = (2 * row - center.row, 2 * col - center.col);
+        for (dr, dc) in self.draw_symmetry.images(offset) {
+            let image = self.neighbor_index(
+                (center.row + dr).div_euclid(2),
+                (center.col + dc).div_euclid(2),
+            );
+            if let Some(image) = image {
+                if !images.contains(&image) {
+                    images.push(image);
+                }
+            }
+        }
+        images
+    }
+
+    /// Draws the live cells of an RLE pattern with its top-left corner at
+    /// `at`, leaving the cells under its dead ones as they are.
+    pub fn paste_rle(&mut self, rle: &str, at: impl Into<Coord>) -> Result<()> {
+        let at = at.into();
+        let (_, rle) = parse_rle(rle.into()).map_err(|e| Error::Parse(format!("{:?}", e)))?;
+        for run in &rle.content {
+            self.tag_state(run.1)?;
+        }
+        let (mut row, mut col) = (at.row, at.col);
+        for RleTagSequence(count, tag) in rle.content {
+            let count = count as i32;
+            match tag {
+                RleTag::NextLine => {
+                    row += count;
+                    col = at.col;
+                }
+                RleTag::Dead => col += count,
+                RleTag::Alive | RleTag::State(_) => {
+                    let state = self.tag_state(tag)?;
+                    for _ in 0..count {
+                        self.paint(row, col, state);
+                        col += 1;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Draws a straight line from `from` to `to`, both included, with
+    /// Bresenham's algorithm.
+    ///
+    /// Like the other drawing tools, it draws in a `Cell` or, under an
+    /// automaton, in any state of it such as a `Wire`.
+    pub fn draw_line(
+        &mut self,
+        from: impl Into<Coord>,
+        to: impl Into<Coord>,
+        state: impl Into<u8>,
+    ) {
+        let (from, to, state) = (from.into(), to.into(), state.into());
+        let (drow, dcol) = ((to.row - from.row).abs(), -(to.col - from.col).abs());
+        let (step_row, step_col) = ((to.row - from.row).signum(), (to.col - from.col).signum());
+        let (mut row, mut col) = (from.row, from.col);
+        let mut error = drow + dcol;
+        loop {
+            self.paint(row, col, state);
+            if (row, col) == (to.row, to.col) {
+                break;
+            }
+            let doubled = 2 * error;
+            if doubled >= dcol {
+                error += dcol;
+                row += step_row;
+            }
+            if doubled <= drow {
+                error += drow;
+                col += step_col;
+            }
+        }
+    }
+
+    pub fn draw_rect(&mut self, rect: &Rect<i32>, state: impl Into<u8>, fill: Fill) {
+        let state = state.into();
+        let (bottom, right) = (rect.y + rect.height - 1, rect.x + rect.width - 1);
+        for row in rect.y..=bottom {
+            for col in rect.x..=right {
+                let edge = row == rect.y || row == bottom || col == rect.x || col == right;
+                if fill == Fill::Solid || edge {
+                    self.paint(row, col, state);
+                }
+            }
+        }
+    }
+
+    /// Draws the ellipse inscribed in `rect`. The outline is made of the
+    /// cells of the ellipse next to a cell outside of it, so it has no gaps.
+    pub fn draw_ellipse(&mut self, rect: &Rect<i32>, state: impl Into<u8>, fill: Fill) {
+        let state = state.into();
+        let inside = |row: i32, col: i32| {
+            // In half cells from the center of the rectangle.
+            let dx = i64::from(2 * col - (2 * rect.x + rect.width - 1));
+            let dy = i64::from(2 * row - (2 * rect.y + rect.height - 1));
+            let (w, h) = (i64::from(rect.width), i64::from(rect.height));
+            dx * dx * h * h + dy * dy * w * w <= w * w * h * h
+        };
+        for row in rect.y..rect.y + rect.height {
+            for col in rect.x..rect.x + rect.width {
+                if !inside(row, col) {
+                    continue;
+                }
+                let edge = !(inside(row - 1, col)
+                    && inside(row + 1, col)
+                    && inside(row, col - 1)
+                    && inside(row, col + 1));
+                if fill == Fill::Solid || edge {
+                    self.paint(row, col, state);
+                }
+            }
+        }
+    }
+
+    /// Sets to `state` the region of cells in the same state as the one at
+    /// `at` and connected to it.
+    pub fn flood_fill(
+        &mut self,
+        at: impl Into<Coord>,
+        state: impl Into<u8>,
+        connectivity: Connectivity,
+    ) {
+        let (at, state) = (at.into(), state.into());
+        let start = match self.neighbor_index(at.row, at.col) {
+            Some(idx) => idx,
+            None => return,
+        };
+        let target = self.state_at(start as usize);
+        if target == state {
+            return;
+        }
+
+        // The set's queue doubles as the list of cells left to expand.
+        let mut region = ActiveSet::new(self.cells.len());
+        region.insert(start);
+        let mut next = 0;
+        while let Some(&idx) = region.iter().nth(next) {
+            next += 1;
+            let (row, col) = self.to_coords(idx);
+            for (r, c) in connectivity.offsets() {
+                if let Some(neighbor) = self.neighbor_index(row + r, col + c) {
+                    if self.state_at(neighbor as usize) == target {
+                        region.insert(neighbor);
+                    }
+                }
+            }
+        }
+        for &idx in region.iter() {
+            let (row, col) = self.to_coords(idx);
+            self.set_state((row, col), state);
+        }
+    }
+
+    /// `set_state`, clipped to the board on a plane.
+    fn paint(&mut self, row: i32, col: i32, state: u8) {
+        let on_board = row >= 0 && row < self.height && col >= 0 && col < self.width;
+        if self.topology == Topology::Torus || on_board {
+            self.set_state((row, col), state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Cell;
+
+    fn picture(world: &World) -> Vec<String> {
+        world
+            .cells()
+            .chunks(world.width() as usize)
+            .map(|row| {
+                row.iter()
+                    .map(|c| if *c == Cell::Alive { 'o' } else { '.' })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn rect(x: i32, y: i32, width: i32, height: i32) -> Rect<i32> {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn line_test() {
+        let mut world = World::new(8, 4);
+        world.draw_line((0, 0), (3, 6), Cell::Alive);
+        assert_eq!(
+            picture(&world),
+            vec!["o.......", ".oo.....", "...oo...", ".....oo."]
+        );
+        // Endpoints can be given in any order.
+        let mut reversed = World::new(8, 4);
+        reversed.draw_line((3, 6), (0, 0), Cell::Alive);
+        assert_eq!(reversed.population(), 7);
+
+        let mut world = World::new(5, 5);
+        world.draw_line((4, 0), (0, 4), Cell::Alive);
+        assert_eq!(world.population(), 5);
+        world.draw_line((2, 2), (2, 2), Cell::Dead);
+        assert_eq!(world.population(), 4);
+    }
+
+    #[test]
+    fn rect_test() {
+        let mut world = World::new(6, 5);
+        world.draw_rect(&rect(1, 1, 4, 3), Cell::Alive, Fill::Outline);
+        assert_eq!(
+            picture(&world),
+            vec!["......", ".oooo.", ".o..o.", ".oooo.", "......"]
+        );
+        world.draw_rect(&rect(1, 1, 4, 3), Cell::Alive, Fill::Solid);
+        assert_eq!(world.population(), 12);
+    }
+
+    #[test]
+    fn ellipse_test() {
+        let mut world = World::new(9, 7);
+        world.draw_ellipse(&rect(0, 0, 9, 7), Cell::Alive, Fill::Outline);
+        assert_eq!(
+            picture(&world),
+            vec![
+                "..ooooo..",
+                ".o.....o.",
+                "o.......o",
+                "o.......o",
+                "o.......o",
+                ".o.....o.",
+                "..ooooo..",
+            ]
+        );
+
+        let mut solid = World::new(9, 7);
+        solid.draw_ellipse(&rect(0, 0, 9, 7), Cell::Alive, Fill::Solid);
+        assert!(world.live_cells().all(|c| solid.cell(c) == Cell::Alive));
+        assert_eq!(solid.cell((3, 4)), Cell::Alive);
+        assert_eq!(solid.cell((0, 0)), Cell::Dead);
+    }
+
+    #[test]
+    fn flood_fill_test() {
+        // A diagonal wall: 4-connected fills stop at it, 8-connected ones
+        // go through.
+        let mut world = World::new(4, 4);
+        world.set_topology(Topology::Plane);
+        world.draw_line((0, 3), (3, 0), Cell::Alive);
+        let mut four = world.clone();
+        four.flood_fill((0, 0), Cell::Alive, Connectivity::Four);
+        assert_eq!(picture(&four), vec!["oooo", "ooo.", "oo..", "o..."]);
+        world.flood_fill((0, 0), Cell::Alive, Connectivity::Eight);
+        assert_eq!(world.population(), 16);
+
+        // Filling alive cells with dead ones erases the shape.
+        world.flood_fill((1, 1), Cell::Dead, Connectivity::Four);
+        assert_eq!(world.population(), 0);
+    }
+
+    #[test]
+    fn wrap_and_clip_test() {
+        let mut torus = World::new(6, 6);
+        torus.draw_rect(&rect(4, 4, 4, 4), Cell::Alive, Fill::Outline);
+        assert_eq!(torus.population(), 12);
+        torus.flood_fill((0, 0), Cell::Alive, Connectivity::Four);
+        assert_eq!(torus.population(), 16);
+
+        let mut plane = World::new(6, 6);
+        plane.set_topology(Topology::Plane);
+        plane.draw_rect(&rect(4, 4, 4, 4), Cell::Alive, Fill::Outline);
+        assert_eq!(plane.population(), 3);
+        plane.draw_line((-2, -2), (1, 1), Cell::Alive);
+        assert_eq!(plane.population(), 5);
+    }
+
+    #[test]
+    fn changed_cells_test() {
+        let mut world = World::new(8, 8);
+        world.next_tick();
+        world.reset_changed_cells();
+        world.draw_rect(&rect(2, 2, 3, 3), Cell::Alive, Fill::Outline);
+        let mut changed = world.changed_cells().to_vec();
+        changed.sort_unstable();
+        assert_eq!(changed, vec![18, 19, 20, 26, 28, 34, 35, 36]);
+    }
+
+    #[test]
+    fn mirror_test() {
+        let mut world = World::new(8, 8);
+        // Axis between columns 3 and 4.
+        world.set_draw_symmetry(DrawSymmetry::Horizontal, (0, 7));
+        world.set_cell((2, 1), Cell::Alive);
+        assert_eq!(world.cell((2, 6)), Cell::Alive);
+        world.draw_line((5, 0), (5, 2), Cell::Alive);
+        assert_eq!(picture(&world)[5], "ooo..ooo");
+        world.toggle((2, 6));
+        assert_eq!(world.population(), 6);
+
+        // Toggling a cell on the axis flips it once.
+        let mut world = World::new(8, 8);
+        world.set_draw_symmetry(DrawSymmetry::Both, (6, 6));
+        world.toggle((3, 3));
+        assert_eq!(world.population(), 1);
+        world.toggle((3, 1));
+        assert_eq!(
+            world
+                .live_cells()
+                .map(|c| (c.row, c.col))
+                .collect::<Vec<_>>(),
+            vec![(3, 1), (3, 3), (3, 5)]
+        );
+        world.set_cell((1, 1), Cell::Alive);
+        assert_eq!(world.population(), 7);
+    }
+
+    #[test]
+    fn rotation_test() {
+        let mut world = World::new(9, 9);
+        world.set_draw_symmetry(DrawSymmetry::Rotate90, (8, 8));
+        world.set_cell((1, 4), Cell::Alive);
+        let mut cells: Vec<_> = world.live_cells().map(|c| (c.row, c.col)).collect();
+        cells.sort();
+        assert_eq!(cells, vec![(1, 4), (4, 1), (4, 7), (7, 4)]);
+
+        world.clear();
+        world.set_draw_symmetry(DrawSymmetry::Diagonal, (0, 0));
+        world.draw_rect(&rect(2, 0, 2, 1), Cell::Alive, Fill::Solid);
+        let mut cells: Vec<_> = world.live_cells().map(|c| (c.row, c.col)).collect();
+        cells.sort();
+        assert_eq!(cells, vec![(0, 2), (0, 3), (2, 0), (3, 0)]);
+
+        assert_eq!("90".parse::<DrawSymmetry>(), Ok(DrawSymmetry::Rotate90));
+        assert!("sideways".parse::<DrawSymmetry>().is_err());
+    }
+
+    #[test]
+    fn symmetry_wrap_test() {
+        // Half a turn around the top-left cell: images wrap on a torus and
+        // are dropped on a plane.
+        let mut torus = World::new(8, 8);
+        torus.set_draw_symmetry(DrawSymmetry::Rotate180, (0, 0));
+        torus.set_cell((1, 2), Cell::Alive);
+        assert_eq!(torus.cell((7, 6)), Cell::Alive);
+        // A cell given past the edge is the same cell.
+        torus.set_cell((-1, -1), Cell::Alive);
+        assert_eq!(torus.cell((1, 1)), Cell::Alive);
+        assert_eq!(torus.population(), 4);
+
+        let mut plane = World::new(8, 8);
+        plane.set_topology(Topology::Plane);
+        plane.set_draw_symmetry(DrawSymmetry::Rotate180, (0, 0));
+        plane.set_cell((1, 2), Cell::Alive);
+        assert_eq!(plane.population(), 1);
+    }
+
+    #[test]
+    fn paste_test() {
+        let mut world = World::new(10, 10);
+        world.set_cell((1, 0), Cell::Alive);
+        world.paste_rle("x = 3, y = 3\nbo$2bo$3o!", (0, 0)).unwrap();
+        // Cells under the dead cells of the pattern are kept.
+        assert_eq!(world.population(), 6);
+
+        let mut world = World::new(10, 10);
+        world.set_draw_symmetry(DrawSymmetry::Vertical, (9, 0));
+        world.paste_rle("x = 3, y = 3\nbo$2bo$3o!", (0, 0)).unwrap();
+        assert_eq!(picture(&world)[9], ".o........");
+        assert_eq!(picture(&world)[7], "ooo.......");
+        assert_eq!(world.population(), 10);
+        assert!(world.paste_rle("not a pattern", (0, 0)).is_err());
+    }
+}
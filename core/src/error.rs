@@ -0,0 +1,59 @@
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The RLE pattern could not be parsed.
+    Parse(String),
+    InvalidRule(String),
+    UnknownTopology(String),
+    UnknownSymmetry(String),
+    /// The image could not be decoded.
+    Image(String),
+    /// The bytes are not a snapshot this version can load.
+    InvalidSnapshot(String),
+    /// No pattern of the library has this id.
+    UnknownPattern(String),
+    UnknownPatternKind(String),
+    InvalidApgcode(String),
+    /// The object doesn't come back to its initial shape within
+    /// `apgcode::MAX_PERIOD` generations, or dies.
+    NotPeriodic,
+    /// Flood fill connectivity other than 4 or 8.
+    UnknownConnectivity(String),
+    /// The `.rule` file could not be parsed.
+    InvalidRuleTable(String),
+    /// No built-in automaton has this name.
+    UnknownAutomaton(String),
+    /// `previous_tick` can't undo the last generation.
+    NotReversible(String),
+    InvalidTurmite(String),
+    /// The search can't run on this world.
+    Search(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Parse(e) => write!(f, "Failed to parse rle string: {}", e),
+            Error::InvalidRule(rule) => write!(f, "Invalid rule: {}", rule),
+            Error::UnknownTopology(topology) => write!(f, "Unknown topology: {}", topology),
+            Error::UnknownSymmetry(symmetry) => write!(f, "Unknown symmetry: {}", symmetry),
+            Error::Image(e) => write!(f, "Invalid image data: {}", e),
+            Error::InvalidSnapshot(e) => write!(f, "Invalid snapshot: {}", e),
+            Error::UnknownPattern(id) => write!(f, "Unknown pattern: {}", id),
+            Error::UnknownPatternKind(kind) => write!(f, "Unknown pattern kind: {}", kind),
+            Error::InvalidApgcode(code) => write!(f, "Invalid apgcode: {}", code),
+            Error::NotPeriodic => write!(f, "Not a still life, oscillator or spaceship"),
+            Error::UnknownConnectivity(c) => write!(f, "Unknown connectivity: {}", c),
+            Error::InvalidRuleTable(e) => write!(f, "Invalid rule table: {}", e),
+            Error::UnknownAutomaton(name) => write!(f, "Unknown automaton: {}", name),
+            Error::NotReversible(e) => write!(f, "Cannot run backwards: {}", e),
+            Error::InvalidTurmite(e) => write!(f, "Invalid turmite: {}", e),
+            Error::Search(e) => write!(f, "Cannot search: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+pub type Result<T> = std::result::Result<T, Error>;
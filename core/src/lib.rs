@@ -0,0 +1,866 @@
+//! Game of Life engine: the `World` grid and its stepping, RLE parsing,
+//! rendering to RGBA buffers and random soups.
+//!
+//! This crate is plain Rust; the wasm bindings live in the `game-of-life`
+//! crate.
+
+use image::GenericImageView;
+
+use std::fmt::Write;
+
+mod active_set;
+pub mod apgcode;
+pub mod automaton;
+pub mod block;
+pub mod census;
+pub mod draw;
+mod error;
+pub mod library;
+#[cfg(feature = "parallel")]
+mod parallel;
+pub mod parser;
+pub mod predecessor;
+pub mod renderer;
+pub mod rule;
+pub mod ship_search;
+pub mod snapshot;
+pub mod soup;
+pub mod stable;
+pub mod stream;
+pub mod table;
+pub mod turmite;
+pub mod wireworld;
+pub mod writer;
+
+pub use automaton::Automaton;
+pub use block::BlockRule;
+pub use census::Census;
+pub use draw::{Connectivity, DrawSymmetry, Fill};
+pub use error::{Error, Result};
+pub use predecessor::Predecessor;
+pub use renderer::Renderer;
+pub use rule::{Rule, Topology};
+pub use ship_search::{SearchEnd, SearchEvent, ShipSearch};
+pub use soup::Symmetry;
+pub use stable::Stable;
+pub use turmite::{Ant, Direction, Turmite};
+pub use wireworld::Wire;
+
+/// Position of a cell on the board. Coordinates outside the board wrap
+/// around it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Coord {
+    pub row: i32,
+    pub col: i32,
+}
+
+impl Coord {
+    pub fn new(row: i32, col: i32) -> Coord {
+        Coord { row, col }
+    }
+}
+
+impl From<(i32, i32)> for Coord {
+    fn from((row, col): (i32, i32)) -> Coord {
+        Coord { row, col }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rect<N> {
+    pub x: N,
+    pub y: N,
+    pub width: N,
+    pub height: N,
+}
+
+#[derive(Clone)]
+pub struct World {
+    width: i32,
+    height: i32,
+    cells: Vec<Cell>,
+    cache: Vec<Cell>,
+    generations: u32,
+    changed_cells: Vec<i32>,
+    /// Cells changed since the last tick, by the tick or by edits: every
+    /// other cell is the same in `cells` and `cache`.
+    active: active_set::ActiveSet,
+    to_check: active_set::ActiveSet,
+    draw_symmetry: draw::DrawSymmetry,
+    symmetry_center: Coord,
+    transitions: Option<Vec<u32>>,
+    rule: Rule,
+    topology: Topology,
+    /// Runs instead of `rule` when set, with the state of every cell in
+    /// `states`; both state buffers are empty otherwise.
+    automaton: Option<Automaton>,
+    states: Vec<u8>,
+    state_cache: Vec<u8>,
+    ants: Vec<turmite::Ant>,
+    /// Bands `next_tick` splits big boards into, 0 for the size of the
+    /// rayon pool, which is only looked up when stepping so that the pool
+    /// can still be configured after worlds are created.
+    #[cfg(feature = "parallel")]
+    threads: usize,
+}
+
+const NEIGHBORHOOD: [(i32, i32); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
+/// Transition stamp of cells that have been dead since age tracking started.
+const NEVER_ALIVE: u32 = u32::MAX;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Cell {
+    Dead = 0,
+    Alive = 1,
+}
+
+/// The state of the cell.
+impl From<Cell> for u8 {
+    fn from(cell: Cell) -> u8 {
+        cell as u8
+    }
+}
+
+use image::Pixel;
+impl std::convert::From<image::Rgba<u8>> for Cell {
+    fn from(a: image::Rgba<u8>) -> Self {
+        let l = a.to_luma();
+        if l.data[0] < 255 / 2 {
+            Cell::Alive
+        } else {
+            Cell::Dead
+        }
+    }
+}
+
+fn flatten<T>(o: Option<Option<T>>) -> Option<T> {
+    match o {
+        Some(Some(t)) => Some(t),
+        _ => None,
+    }
+}
+
+pub struct FirstN<'a, I> {
+    inner: &'a mut I,
+    n: usize,
+    count: usize,
+}
+
+impl<'a, I: 'a + Iterator> Iterator for FirstN<'a, I> {
+    type Item = <I as Iterator>::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.count >= self.n {
+            return None;
+        }
+        self.count += 1;
+        self.inner.next()
+    }
+}
+
+fn first_n<I>(i: &mut I, n: usize) -> FirstN<'_, I> {
+    FirstN {
+        inner: i,
+        n,
+        count: 0,
+    }
+}
+
+impl World {
+    pub fn new(width: i32, height: i32) -> World {
+        let data = vec![Cell::Dead; (width * height) as usize];
+
+        World {
+            width,
+            height,
+            cells: data.clone(),
+            cache: data,
+            generations: 0,
+            changed_cells: Vec::new(),
+            // Under rules with B0, even an empty board changes.
+            active: active_set::ActiveSet::full((width * height) as usize),
+            to_check: active_set::ActiveSet::new((width * height) as usize),
+            draw_symmetry: draw::DrawSymmetry::None,
+            symmetry_center: Coord::new(0, 0),
+            transitions: None,
+            rule: Rule::default(),
+            topology: Topology::default(),
+            automaton: None,
+            states: Vec::new(),
+            state_cache: Vec::new(),
+            ants: Vec::new(),
+            #[cfg(feature = "parallel")]
+            threads: 0,
+        }
+    }
+
+    /// Builds a world the size of the image, where dark pixels are alive.
+    pub fn from_image(data: &[u8]) -> Result<World> {
+        let im = image::load_from_memory(data)
+            .map_err(|e| Error::Image(e.to_string()))?
+            .grayscale();
+        let mut world = World::new(im.width() as i32, im.height() as i32);
+        for pixel in im.pixels() {
+            let (x, y, pix) = pixel;
+            world.set_cell((y as i32, x as i32), Cell::from(pix));
+        }
+        Ok(world)
+    }
+
+    /// Parses an RLE pattern and places it with `load_rle`.
+    pub fn load_rle_str(&mut self, pattern: &str) -> Result<()> {
+        let (_, rle) =
+            parser::parse_rle(pattern.into()).map_err(|e| Error::Parse(format!("{:?}", e)))?;
+        self.load_rle(rle)
+    }
+
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    pub fn set_rule(&mut self, rule: Rule) {
+        self.rule = rule;
+        self.mark_all_changed();
+    }
+
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    pub fn set_topology(&mut self, topology: Topology) {
+        self.topology = topology;
+        self.mark_all_changed();
+    }
+
+    pub fn generations(&self) -> u32 {
+        self.generations
+    }
+
+    pub fn population(&self) -> u32 {
+        self.cells.iter().filter(|c| **c == Cell::Alive).count() as u32
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    /// The smallest rectangle containing every alive cell.
+    pub fn bounding_box(&self) -> Option<Rect<usize>> {
+        self.pattern_boundaries()
+    }
+
+    fn pattern_boundaries(&self) -> Option<Rect<usize>> {
+        let first_line_idx = self.cells.iter().position(|c| *c == Cell::Alive);
+        let (first_line, _) = self.to_coords(first_line_idx? as i32);
+        let first_line = first_line as usize;
+
+        let last_line_idx = self.cells.iter().rposition(|c| *c == Cell::Alive);
+        let (last_line, _) = self.to_coords(last_line_idx? as i32);
+        let last_line = last_line as usize;
+
+        let mut first_column = self.width as usize;
+        let mut last_column = 0;
+
+        let mut cells = self.cells.iter();
+        for _ in 0..self.height {
+            let mut row = first_n(&mut cells, self.width as usize).enumerate();
+            let first_col_alive = row.find(|(_, c)| **c == Cell::Alive);
+            let last_col_alive = row
+                .filter(|(_, c)| **c == Cell::Alive)
+                .last()
+                .or(first_col_alive);
+            if let Some((pos, _)) = first_col_alive {
+                first_column = ::std::cmp::min(first_column, pos);
+            }
+            if let Some((pos, _)) = last_col_alive {
+                last_column = ::std::cmp::max(last_column, pos);
+            }
+        }
+        Some(Rect {
+            x: first_column,
+            y: first_line,
+            width: last_column - first_column + 1,
+            height: last_line - first_line + 1,
+        })
+    }
+
+    /// The pattern as RLE, with a `#R` line shifting it from the center of
+    /// the board the way `load_rle` reads it back.
+    pub fn export_rle(&self) -> String {
+        let pattern_boundaries = self.pattern_boundaries();
+
+        if pattern_boundaries.is_none() {
+            return "".to_string();
+        }
+        let bounds = pattern_boundaries.unwrap();
+
+        let mut buff = String::new();
+
+        let center_col = self.width / 2;
+        let center_row = self.height / 2;
+        write!(
+            &mut buff,
+            "#R {} {}\nx = {}, y = {}, rule = {}\n",
+            (bounds.x + bounds.width / 2) as i32 - center_col,
+            (bounds.y + bounds.height / 2) as i32 - center_row,
+            bounds.width,
+            bounds.height,
+            self.rule_name()
+        )
+        .ok();
+
+        self.write_pattern(bounds, buff)
+            .expect("Writing to a String cannot fail")
+    }
+
+    fn write_pattern<W: std::fmt::Write>(
+        &self,
+        bounds: Rect<usize>,
+        w: W,
+    ) -> std::result::Result<W, std::fmt::Error> {
+        let mut writer = if self.automaton.is_some() {
+            writer::RleWriter::multi_state(w)
+        } else {
+            writer::RleWriter::new(w)
+        };
+        for row in bounds.y..bounds.y + bounds.height {
+            for col in bounds.x..bounds.x + bounds.width {
+                let tag = match self.state((row as i32, col as i32)) {
+                    0 => parser::RleTag::Dead,
+                    _ if self.automaton.is_none() => parser::RleTag::Alive,
+                    state => parser::RleTag::State(state),
+                };
+                writer.push(1, tag)?;
+            }
+            writer.push(1, parser::RleTag::NextLine)?;
+        }
+        writer.finish()
+    }
+
+    pub fn resize(&mut self, width: i32, height: i32) {
+        let mut new_world = World::new(width, height);
+        new_world.set_age_tracking(self.transitions.is_some());
+        new_world.rule = self.rule;
+        new_world.topology = self.topology;
+        new_world.draw_symmetry = self.draw_symmetry;
+        new_world.symmetry_center = self.symmetry_center;
+        #[cfg(feature = "parallel")]
+        {
+            new_world.threads = self.threads;
+        }
+        new_world.set_automaton(self.automaton.clone());
+        let copy_end_width = ::std::cmp::min(width, self.width);
+        let copy_end_height = ::std::cmp::min(height, self.height);
+        for row in 0..copy_end_height {
+            for col in 0..copy_end_width {
+                let idx = new_world.get_index(row, col);
+                new_world.put_state(idx, self.state((row, col)));
+            }
+        }
+        new_world.ants = self
+            .ants
+            .iter()
+            .filter(|ant| ant.position().row < height && ant.position().col < width)
+            .cloned()
+            .collect();
+
+        ::std::mem::swap(self, &mut new_world);
+    }
+
+    #[inline(always)]
+    fn get_index(&self, row: i32, col: i32) -> i32 {
+        let col = col.rem_euclid(self.width);
+        let row = row.rem_euclid(self.height);
+        row * self.width + col
+    }
+
+    /// Index of the cell at `(row, col)` as seen from inside the board:
+    /// coordinates past the edges wrap around on a torus and point to
+    /// nothing on a plane.
+    #[inline(always)]
+    fn neighbor_index(&self, row: i32, col: i32) -> Option<i32> {
+        match self.topology {
+            Topology::Torus => Some(self.get_index(row, col)),
+            Topology::Plane => {
+                if row < 0 || row >= self.height || col < 0 || col >= self.width {
+                    None
+                } else {
+                    Some(row * self.width + col)
+                }
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn get(&self, row: i32, col: i32) -> Cell {
+        self.cells[self.get_index(row, col) as usize]
+    }
+
+    pub fn cell(&self, at: impl Into<Coord>) -> Cell {
+        let at = at.into();
+        self.get(at.row, at.col)
+    }
+
+    /// Sets the cell at `at`, and its images under the drawing symmetry.
+    pub fn set_cell(&mut self, at: impl Into<Coord>, t: Cell) {
+        for idx in self.images(at.into()) {
+            self.put(idx, t);
+        }
+    }
+
+    /// `set_cell` without the drawing symmetry.
+    fn put_cell(&mut self, at: impl Into<Coord>, t: Cell) {
+        let at = at.into();
+        let idx = self.get_index(at.row, at.col);
+        self.put(idx, t);
+    }
+
+    fn put(&mut self, idx: i32, t: Cell) {
+        self.changed_cells.push(idx);
+        self.active.insert(idx);
+        let idx = idx as usize;
+        if self.cells[idx] != t {
+            self.record_transition(idx);
+        }
+        self.cells[idx] = t;
+        if let Some(state) = self.states.get_mut(idx) {
+            *state = t as u8;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for idx in 0..self.cells.len() {
+            if self.cells[idx] == Cell::Alive {
+                self.record_transition(idx);
+            }
+        }
+        self.cells.iter_mut().for_each(|cell| *cell = Cell::Dead);
+        self.states.iter_mut().for_each(|state| *state = 0);
+        self.mark_all_changed();
+    }
+
+    /// Flips the cell at `at`, and gives its images under the drawing
+    /// symmetry the same new state.
+    pub fn toggle(&mut self, at: impl Into<Coord>) {
+        let at = at.into();
+        let t = match self.cell(at) {
+            Cell::Dead => Cell::Alive,
+            Cell::Alive => Cell::Dead,
+        };
+        self.set_cell(at, t);
+    }
+
+    /// Every cell of the board, row by row.
+    pub fn cells(&self) -> &[Cell] {
+        &self.cells
+    }
+
+    pub fn live_cells(&self) -> impl Iterator<Item = Coord> + '_ {
+        self.cells
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| **c == Cell::Alive)
+            .map(move |(idx, _)| {
+                let (row, col) = self.to_coords(idx as i32);
+                Coord { row, col }
+            })
+    }
+
+    fn alive_neighbors(&self, row: i32, col: i32) -> u8 {
+        NEIGHBORHOOD
+            .iter()
+            .filter_map(|(r, c)| self.neighbor_index(row + r, col + c))
+            .map(|idx| self.cells[idx as usize] as u8)
+            .sum()
+    }
+
+    /// Indexes of the cells changed by the last tick and by the edits made
+    /// since then, until `reset_changed_cells` is called.
+    pub fn changed_cells(&self) -> &[i32] {
+        &self.changed_cells
+    }
+
+    pub fn reset_changed_cells(&mut self) {
+        self.changed_cells.clear();
+    }
+
+    /// Computes the next generation of the cells, then moves the ants.
+    pub fn next_tick(&mut self) {
+        self.step_cells();
+        if !self.ants.is_empty() {
+            self.move_ants();
+        }
+    }
+
+    fn step_cells(&mut self) {
+        match self.automaton {
+            Some(Automaton::Block(_)) => return self.step_block_rule(),
+            Some(_) => return self.step_automaton(),
+            None => {}
+        }
+
+        #[cfg(feature = "parallel")]
+        {
+            if self.use_bands() {
+                self.changed_cells = self.step_bands();
+                self.finish_tick();
+                return;
+            }
+        }
+
+        let mut to_check = std::mem::take(&mut self.to_check);
+        to_check.clear();
+        for &idx in self.active.iter() {
+            let (row, col) = self.to_coords(idx);
+            to_check.insert(idx);
+            for (r, c) in NEIGHBORHOOD.iter() {
+                if let Some(neighbor) = self.neighbor_index(row + r, col + c) {
+                    to_check.insert(neighbor);
+                }
+            }
+        }
+
+        self.changed_cells.clear();
+        for &idx in to_check.iter() {
+            let (row, col) = self.to_coords(idx);
+            let cell = self.cells[idx as usize];
+            let neighbors = self.alive_neighbors(row, col);
+            let alive = match cell {
+                Cell::Alive => self.rule.survives(neighbors),
+                Cell::Dead => self.rule.born(neighbors),
+            };
+            let next_cell = if alive { Cell::Alive } else { Cell::Dead };
+            if cell != next_cell {
+                self.changed_cells.push(idx);
+            }
+            self.cache[idx as usize] = next_cell;
+        }
+        self.to_check = to_check;
+
+        self.finish_tick();
+    }
+
+    /// Makes the generation computed into `cache` the current one, given
+    /// the cells it changed in `changed_cells`.
+    fn finish_tick(&mut self) {
+        ::std::mem::swap(&mut self.cells, &mut self.cache);
+        self.end_tick();
+    }
+
+    /// Bookkeeping of a tick whose changes are in `changed_cells`.
+    fn end_tick(&mut self) {
+        self.generations += 1;
+        self.note_changes();
+    }
+
+    /// Makes the next tick look around `changed_cells`, and records them.
+    fn note_changes(&mut self) {
+        self.active.clear();
+        for &idx in self.changed_cells.iter() {
+            self.active.insert(idx);
+        }
+
+        if self.transitions.is_some() {
+            for idx in 0..self.changed_cells.len() {
+                let idx = self.changed_cells[idx] as usize;
+                self.record_transition(idx);
+            }
+        }
+    }
+
+    /// Enables or disables the per-cell age counters. Cells alive when
+    /// tracking starts are considered born at the current generation.
+    pub fn set_age_tracking(&mut self, enabled: bool) {
+        if !enabled {
+            self.transitions = None;
+            return;
+        }
+        if self.transitions.is_none() {
+            let generations = self.generations;
+            self.transitions = Some(
+                self.cells
+                    .iter()
+                    .map(|c| match c {
+                        Cell::Alive => generations,
+                        Cell::Dead => NEVER_ALIVE,
+                    })
+                    .collect(),
+            );
+        }
+    }
+
+    pub fn age_tracking(&self) -> bool {
+        self.transitions.is_some()
+    }
+
+    /// For an alive cell, the number of generations it has been alive; for a
+    /// dead cell, the number of generations since it died. `None` for cells
+    /// that have not been alive since tracking started, and for every cell
+    /// when tracking is disabled.
+    pub fn age(&self, at: impl Into<Coord>) -> Option<u32> {
+        let at = at.into();
+        self.age_at(self.get_index(at.row, at.col) as usize)
+    }
+
+    /// The `age` of every cell, row by row.
+    pub fn ages(&self) -> impl Iterator<Item = Option<u32>> + '_ {
+        (0..self.cells.len()).map(move |idx| self.age_at(idx))
+    }
+
+    fn record_transition(&mut self, idx: usize) {
+        let generations = self.generations;
+        if let Some(transitions) = self.transitions.as_mut() {
+            transitions[idx] = generations;
+        }
+    }
+
+    fn age_at(&self, idx: usize) -> Option<u32> {
+        match self.transitions {
+            Some(ref transitions) if transitions[idx] != NEVER_ALIVE => {
+                Some(self.generations - transitions[idx])
+            }
+            _ => None,
+        }
+    }
+
+    fn to_coords(&self, mut idx: i32) -> (i32, i32) {
+        if idx < 0 {
+            idx += self.width * self.height;
+        }
+        let row = idx / self.width;
+        let col = idx % self.width;
+        (row, col)
+    }
+
+    /// Places `rle` centered on the board, shifted by its `#P`/`#R`
+    /// coordinates if any. Patterns with cells in states the board doesn't
+    /// have are rejected before anything is placed.
+    pub fn load_rle(&mut self, rle: parser::Rle) -> Result<()> {
+        for run in &rle.content {
+            self.tag_state(run.1)?;
+        }
+        let top_left = self.rle_top_left(&rle.comments, &rle.size);
+        let mut cursor = top_left;
+        rle.content
+            .into_iter()
+            .try_for_each(|run| self.place_run(top_left, &mut cursor, run))
+    }
+
+    /// Where the top-left corner of an RLE pattern with this header lands,
+    /// as `(col, row)`.
+    fn rle_top_left(
+        &self,
+        comments: &[parser::RleComment],
+        size: &parser::RleFirstLine,
+    ) -> (i32, i32) {
+        let coords = comments
+            .iter()
+            .map(|c| match c {
+                parser::RleComment::Coordinates(x, y) => Some((*x, *y)),
+                _ => None,
+            })
+            .find(Option::is_some);
+        let (x, y) = flatten(coords).unwrap_or((0, 0));
+
+        let origin_x = self.width / 2;
+        let origin_y = self.height / 2;
+
+        (
+            origin_x + x - (size.0 / 2) as i32,
+            origin_y + y - (size.1 / 2) as i32,
+        )
+    }
+
+    /// Writes one run of cells at `cursor`, a `(col, row)` pair, and moves it
+    /// past the run.
+    fn place_run(
+        &mut self,
+        top_left: (i32, i32),
+        cursor: &mut (i32, i32),
+        run: parser::RleTagSequence,
+    ) -> Result<()> {
+        match run {
+            parser::RleTagSequence(count, parser::RleTag::NextLine) => {
+                cursor.1 += count as i32;
+                cursor.0 = top_left.0;
+            }
+            parser::RleTagSequence(count, tag) => {
+                let state = self.tag_state(tag)?;
+                for _ in 0..count {
+                    let idx = self.get_index(cursor.1, cursor.0);
+                    self.put_state(idx, state);
+                    cursor.0 += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Makes the next tick look at every cell, which is needed whenever the
+    /// evolution of unchanged cells may differ from the previous tick.
+    fn mark_all_changed(&mut self) {
+        self.changed_cells = (0..self.cells.len() as i32).collect();
+        self.active.insert_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn age_tracking_test() {
+        let mut world = World::new(8, 8);
+        world.set_age_tracking(true);
+        // Blinker: the middle cell never dies, the tips alternate.
+        world.set_cell((3, 2), Cell::Alive);
+        world.set_cell((3, 3), Cell::Alive);
+        world.set_cell((3, 4), Cell::Alive);
+
+        world.next_tick();
+        world.next_tick();
+        world.next_tick();
+
+        assert_eq!(world.age((3, 3)), Some(3));
+        assert_eq!(world.age((2, 3)), Some(0));
+        assert_eq!(world.age((3, 2)), Some(0));
+        assert_eq!(world.age((0, 0)), None);
+
+        world.next_tick();
+        assert_eq!(world.age((2, 3)), Some(0));
+        assert_eq!(world.age((3, 2)), Some(0));
+        assert_eq!(world.age((3, 3)), Some(4));
+    }
+
+    #[test]
+    fn age_tracking_disabled_test() {
+        let mut world = World::new(4, 4);
+        world.set_cell((1, 1), Cell::Alive);
+        world.next_tick();
+        assert_eq!(world.age((1, 1)), None);
+
+        world.set_cell((1, 1), Cell::Alive);
+        world.set_age_tracking(true);
+        world.next_tick();
+        assert_eq!(world.age((1, 1)), Some(0));
+        world.next_tick();
+        assert_eq!(world.age((1, 1)), Some(1));
+    }
+
+    #[test]
+    fn border_cells_test() {
+        let mut world = World::new(4, 4);
+        world.toggle((0, 0));
+        world.toggle((0, 3));
+        world.toggle((3, 0));
+        world.next_tick();
+        // On a torus the three cells are neighbors around the corner.
+        assert_eq!(world.get(3, 3), Cell::Alive);
+        assert_eq!(world.population(), 4);
+    }
+
+    #[test]
+    fn edit_after_reset_test() {
+        // Cells changed by a tick must be stepped by the next one even when
+        // `changed_cells` was reset and the board edited in between.
+        let mut world = World::new(16, 16);
+        world.load_rle_str("x = 3, y = 3\nbo$2bo$3o!").unwrap();
+        let mut reference = world.clone();
+        for _ in 0..8 {
+            world.next_tick();
+            reference.next_tick();
+            world.reset_changed_cells();
+            world.set_cell((0, 15), Cell::Dead);
+            reference.set_cell((0, 15), Cell::Dead);
+            assert_eq!(world.cells(), reference.cells());
+        }
+        assert_eq!(world.population(), 5);
+    }
+
+    #[test]
+    fn plane_topology_test() {
+        let mut world = World::new(5, 5);
+        world.set_topology(Topology::Plane);
+        // A blinker against the top edge loses the cell that would be
+        // outside the board.
+        world.set_cell((0, 1), Cell::Alive);
+        world.set_cell((0, 2), Cell::Alive);
+        world.set_cell((0, 3), Cell::Alive);
+        world.next_tick();
+        assert_eq!(world.get(4, 2), Cell::Dead);
+        assert_eq!(world.get(0, 2), Cell::Alive);
+        assert_eq!(world.get(1, 2), Cell::Alive);
+        assert_eq!(world.population(), 2);
+    }
+
+    #[test]
+    fn live_cells_test() {
+        let mut world = World::new(4, 4);
+        world.set_cell((1, 2), Cell::Alive);
+        world.set_cell(Coord::new(3, 0), Cell::Alive);
+        world.set_cell((-1, -1), Cell::Alive);
+        let cells: Vec<Coord> = world.live_cells().collect();
+        assert_eq!(
+            cells,
+            vec![Coord::new(1, 2), Coord::new(3, 0), Coord::new(3, 3)]
+        );
+    }
+
+    #[test]
+    fn load_rle_str_test() {
+        let mut world = World::new(8, 8);
+        assert!(world.load_rle_str("x = 3, y = 1\n3o!").is_ok());
+        assert_eq!(world.population(), 3);
+        match world.load_rle_str("not a pattern") {
+            Err(Error::Parse(_)) => (),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn load_rle_position_test() {
+        let cells = |rle: &str| {
+            let mut world = World::new(16, 16);
+            world.load_rle_str(rle).unwrap();
+            world.live_cells().collect::<Vec<_>>()
+        };
+        let row = |row, cols: std::ops::Range<i32>| -> Vec<Coord> {
+            cols.map(|col| Coord::new(row, col)).collect()
+        };
+
+        assert_eq!(cells("x = 3, y = 1\n3o!"), row(8, 7..10));
+        // `#P` and `#R` shift the pattern from the center of the board.
+        assert_eq!(cells("#R 2 -3\nx = 3, y = 1\n3o!"), row(5, 9..12));
+        assert_eq!(cells("#P -7 1\nx = 3, y = 1\n3o!"), row(9, 0..3));
+    }
+
+    #[test]
+    fn rule_test() {
+        let mut world = World::new(8, 8);
+        // Under B1/S, a single cell grows into a hollow ring of 8 cells.
+        world.set_rule("B1/S".parse().unwrap());
+        world.set_cell((4, 4), Cell::Alive);
+        world.next_tick();
+        assert_eq!(world.population(), 8);
+        assert_eq!(world.get(4, 4), Cell::Dead);
+        assert_eq!(world.rule().to_string(), "B1/S");
+    }
+}
@@ -0,0 +1,282 @@
+//! The bundled pattern catalogue.
+//!
+//! Every file of `patterns/` is embedded in the library. Names, authors and
+//! descriptions come from the `#N`, `#O` and `#C` lines of each file; the
+//! kind and period, which RLE files don't carry, are annotated here.
+
+use std::fmt;
+use std::str::FromStr;
+
+use super::parser::{parse_rle, RleComment};
+use super::{Error, Result, World};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PatternKind {
+    StillLife,
+    Oscillator,
+    Spaceship,
+    /// Burns along a line, like a fuse.
+    Fuse,
+    /// Dies out, leaving behind a short-lived spark.
+    Spark,
+}
+
+impl fmt::Display for PatternKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            PatternKind::StillLife => "still life",
+            PatternKind::Oscillator => "oscillator",
+            PatternKind::Spaceship => "spaceship",
+            PatternKind::Fuse => "fuse",
+            PatternKind::Spark => "spark",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for PatternKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_lowercase().replace(['-', '_'], " ").as_str() {
+            "still life" | "still" => Ok(PatternKind::StillLife),
+            "oscillator" => Ok(PatternKind::Oscillator),
+            "spaceship" => Ok(PatternKind::Spaceship),
+            "fuse" => Ok(PatternKind::Fuse),
+            "spark" => Ok(PatternKind::Spark),
+            _ => Err(Error::UnknownPatternKind(s.to_string())),
+        }
+    }
+}
+
+struct Entry {
+    id: &'static str,
+    kind: PatternKind,
+    period: Option<u32>,
+    rle: &'static str,
+}
+
+macro_rules! entry {
+    ($id:expr, $kind:ident, $period:expr) => {
+        Entry {
+            id: $id,
+            kind: PatternKind::$kind,
+            period: $period,
+            rle: include_str!(concat!("../../patterns/", $id, ".rle")),
+        }
+    };
+}
+
+const CATALOGUE: &[Entry] = &[
+    entry!("24P10", Oscillator, Some(10)),
+    entry!("33p3.1", Oscillator, Some(3)),
+    entry!("3enginecordership", Spaceship, Some(96)),
+    entry!("68P16", Oscillator, Some(16)),
+    entry!("baker", Fuse, Some(4)),
+    entry!("bananaspark", Spark, None),
+    entry!("smiley", Oscillator, Some(8)),
+];
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PatternInfo {
+    /// File name of the pattern, without extension.
+    pub id: &'static str,
+    pub name: String,
+    pub author: Option<String>,
+    /// The `#C` lines, in order.
+    pub comments: Vec<String>,
+    pub kind: PatternKind,
+    pub period: Option<u32>,
+    pub width: usize,
+    pub height: usize,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub rle: &'static str,
+}
+
+impl PatternInfo {
+    fn from_entry(entry: &Entry) -> PatternInfo {
+        let (_, rle) = parse_rle(entry.rle.into()).expect("Bundled patterns are valid");
+        let mut name = None;
+        let mut author = None;
+        let mut comments = Vec::new();
+        for comment in rle.comments {
+            match comment {
+                RleComment::Name(n) => name = Some(n.trim().trim_end_matches(".rle").to_string()),
+                RleComment::Author(a) => author = Some(a.trim().to_string()),
+                RleComment::Comment(c) => comments.push(c.trim().to_string()),
+                _ => {}
+            }
+        }
+
+        PatternInfo {
+            id: entry.id,
+            name: name.unwrap_or_else(|| entry.id.to_string()),
+            author,
+            comments,
+            kind: entry.kind,
+            period: entry.period,
+            width: rle.size.0,
+            height: rle.size.1,
+            rle: entry.rle,
+        }
+    }
+}
+
+/// Criteria for `search`; `None` fields match every pattern. Name and author
+/// match case-insensitive substrings.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Filter {
+    pub name: Option<String>,
+    pub author: Option<String>,
+    pub kind: Option<PatternKind>,
+    pub period: Option<u32>,
+}
+
+impl Filter {
+    fn matches(&self, pattern: &PatternInfo) -> bool {
+        fn contains(haystack: Option<&str>, needle: &Option<String>) -> bool {
+            match needle {
+                Some(needle) => {
+                    haystack.is_some_and(|h| h.to_lowercase().contains(&needle.to_lowercase()))
+                }
+                None => true,
+            }
+        }
+
+        contains(Some(&pattern.name), &self.name)
+            && contains(pattern.author.as_deref(), &self.author)
+            && self.kind.is_none_or(|kind| kind == pattern.kind)
+            && self
+                .period
+                .is_none_or(|period| Some(period) == pattern.period)
+    }
+}
+
+/// Every pattern of the catalogue, sorted by id.
+pub fn patterns() -> Vec<PatternInfo> {
+    CATALOGUE.iter().map(PatternInfo::from_entry).collect()
+}
+
+pub fn search(filter: &Filter) -> Vec<PatternInfo> {
+    patterns()
+        .into_iter()
+        .filter(|p| filter.matches(p))
+        .collect()
+}
+
+pub fn pattern(id: &str) -> Option<PatternInfo> {
+    CATALOGUE
+        .iter()
+        .find(|entry| entry.id == id)
+        .map(PatternInfo::from_entry)
+}
+
+impl World {
+    /// Places the catalogue pattern `id` like `load_rle_str` does.
+    pub fn load_pattern(&mut self, id: &str) -> Result<()> {
+        let pattern = pattern(id).ok_or_else(|| Error::UnknownPattern(id.to_string()))?;
+        self.load_rle_str(pattern.rle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn catalogue_test() {
+        use std::fs;
+
+        // Every file of patterns/ is in the catalogue.
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../patterns");
+        let mut files: Vec<_> = fs::read_dir(dir)
+            .unwrap()
+            .map(|e| {
+                e.unwrap()
+                    .path()
+                    .file_stem()
+                    .unwrap()
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect();
+        files.sort();
+        let ids: Vec<_> = patterns().iter().map(|p| p.id.to_string()).collect();
+        assert_eq!(ids, files);
+
+        let smiley = pattern("smiley").unwrap();
+        assert_eq!(smiley.name, "Smiley");
+        assert_eq!(smiley.author, Some("Achim Flammenkamp".to_string()));
+        assert_eq!(smiley.comments.len(), 2);
+        assert_eq!((smiley.width, smiley.height), (7, 7));
+
+        assert_eq!(pattern("33p3.1").unwrap().name, "33p3.1");
+        assert!(pattern("nope").is_none());
+    }
+
+    #[test]
+    fn oscillator_periods_test() {
+        let filter = Filter {
+            kind: Some(PatternKind::Oscillator),
+            ..Filter::default()
+        };
+        for pattern in search(&filter) {
+            let mut world = World::new(pattern.width as i32 + 8, pattern.height as i32 + 8);
+            world.load_pattern(pattern.id).unwrap();
+            let start = world.cells().to_vec();
+            for generation in 1..=pattern.period.unwrap() {
+                world.next_tick();
+                let back = world.cells() == &start[..];
+                assert_eq!(
+                    back,
+                    generation == pattern.period.unwrap(),
+                    "{}",
+                    pattern.id
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn search_test() {
+        let ids = |filter: Filter| -> Vec<&str> { search(&filter).iter().map(|p| p.id).collect() };
+
+        assert_eq!(
+            ids(Filter {
+                name: Some("CORDER".to_string()),
+                ..Filter::default()
+            }),
+            vec!["3enginecordership"]
+        );
+        assert_eq!(
+            ids(Filter {
+                author: Some("merzenich".to_string()),
+                ..Filter::default()
+            }),
+            vec!["24P10"]
+        );
+        assert_eq!(
+            ids(Filter {
+                kind: Some("oscillator".parse().unwrap()),
+                period: Some(16),
+                ..Filter::default()
+            }),
+            vec!["68P16"]
+        );
+        assert_eq!(ids(Filter::default()).len(), CATALOGUE.len());
+        assert!("glider gun".parse::<PatternKind>().is_err());
+    }
+
+    #[test]
+    fn load_pattern_test() {
+        let mut world = World::new(32, 32);
+        world.load_pattern("baker").unwrap();
+        assert!(world.population() > 0);
+        match world.load_pattern("nope") {
+            Err(Error::UnknownPattern(id)) => assert_eq!(id, "nope"),
+            _ => panic!("expected an unknown pattern error"),
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! Multi-threaded stepping.
+//!
+//! The board is cut into horizontal bands, one per thread. Each band reads
+//! its own rows plus one halo row above and below it, and writes only its
+//! own rows of the next generation, so bands never share mutable state and
+//! the result is exactly the one of the serial path.
+
+use rayon::prelude::*;
+
+use super::{Cell, Topology, World};
+
+/// The band path looks at every cell, which only pays off when a good part
+/// of the board is active: at least one cell in `DENSE_RATIO` changed.
+const DENSE_RATIO: usize = 16;
+
+/// Boards smaller than this are not worth spreading over threads.
+const MIN_CELLS: usize = 64 * 64;
+
+impl World {
+    /// Sets how many bands `next_tick` splits big, busy boards into; 1
+    /// steps on the calling thread only. Defaults to the size of the rayon
+    /// thread pool.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    pub fn threads(&self) -> usize {
+        match self.threads {
+            0 => rayon::current_num_threads(),
+            threads => threads,
+        }
+    }
+
+    pub(crate) fn use_bands(&self) -> bool {
+        self.threads() > 1
+            && self.automaton.is_none()
+            && self.cells.len() >= MIN_CELLS
+            && self.active.len() * DENSE_RATIO >= self.cells.len()
+    }
+
+    /// Computes the next generation of every cell into `cache`, and returns
+    /// the indexes of the cells that changed, in order.
+    pub(crate) fn step_bands(&mut self) -> Vec<i32> {
+        let width = self.width as usize;
+        let height = self.height as usize;
+        let rows_per_band = height.div_ceil(self.threads().min(height));
+
+        let mut cache = std::mem::take(&mut self.cache);
+        let world = &*self;
+        let changed: Vec<Vec<i32>> = cache
+            .par_chunks_mut(rows_per_band * width)
+            .enumerate()
+            .map(|(band, out)| world.step_band(band * rows_per_band, out))
+            .collect();
+        self.cache = cache;
+        changed.concat()
+    }
+
+    fn step_band(&self, first_row: usize, out: &mut [Cell]) -> Vec<i32> {
+        let width = self.width as usize;
+        let torus = self.topology == Topology::Torus;
+        let mut changed = Vec::new();
+        // Alive cells in each column of the three rows around the current
+        // one, with one more column on each side for the edges.
+        let mut sums = vec![0u8; width + 2];
+        for (offset, out_row) in out.chunks_mut(width).enumerate() {
+            let row = (first_row + offset) as i32;
+            let current = self
+                .band_row(row)
+                .expect("Rows of the band are on the board");
+            sums.iter_mut().for_each(|sum| *sum = 0);
+            let rows = [
+                self.band_row(row - 1),
+                Some(current),
+                self.band_row(row + 1),
+            ];
+            for halo in rows.iter().flatten() {
+                for (sum, cell) in sums[1..=width].iter_mut().zip(halo.iter()) {
+                    *sum += *cell as u8;
+                }
+            }
+            if torus {
+                sums[0] = sums[width];
+                sums[width + 1] = sums[1];
+            }
+
+            for (col, next) in out_row.iter_mut().enumerate() {
+                let cell = current[col];
+                let neighbors = sums[col] + sums[col + 1] + sums[col + 2] - cell as u8;
+                let alive = match cell {
+                    Cell::Alive => self.rule.survives(neighbors),
+                    Cell::Dead => self.rule.born(neighbors),
+                };
+                *next = if alive { Cell::Alive } else { Cell::Dead };
+                if *next != cell {
+                    changed.push(row * self.width + col as i32);
+                }
+            }
+        }
+        changed
+    }
+
+    /// The cells of `row`, which may be a halo row past an edge of the board.
+    fn band_row(&self, row: i32) -> Option<&[Cell]> {
+        let row = match self.topology {
+            Topology::Torus => row.rem_euclid(self.height),
+            Topology::Plane if row < 0 || row >= self.height => return None,
+            Topology::Plane => row,
+        } as usize;
+        let width = self.width as usize;
+        Some(&self.cells[row * width..(row + 1) * width])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn soup(width: i32, height: i32, topology: Topology, seed: u32) -> World {
+        let mut world = World::new(width, height);
+        world.set_topology(topology);
+        world.randomize(0.4, seed);
+        world
+    }
+
+    fn sorted(cells: &[i32]) -> Vec<i32> {
+        let mut cells = cells.to_vec();
+        cells.sort_unstable();
+        cells.dedup();
+        cells
+    }
+
+    #[test]
+    fn bands_match_serial_test() {
+        for &(width, height) in &[(97, 61), (64, 64), (3, 130), (130, 2), (1, 40), (2, 70)] {
+            for &topology in &[Topology::Torus, Topology::Plane] {
+                let mut serial = soup(width, height, topology, 7);
+                serial.set_threads(1);
+                let mut banded = serial.clone();
+                banded.set_threads(5);
+                for _ in 0..30 {
+                    serial.next_tick();
+                    banded.changed_cells = banded.step_bands();
+                    banded.finish_tick();
+                    assert_eq!(serial.cells(), banded.cells());
+                    assert_eq!(
+                        sorted(serial.changed_cells()),
+                        banded.changed_cells().to_vec()
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn next_tick_threads_test() {
+        let mut serial = soup(256, 200, Topology::Torus, 3);
+        serial.set_age_tracking(true);
+        serial.set_threads(1);
+        let mut threaded = serial.clone();
+        threaded.set_threads(8);
+        for _ in 0..100 {
+            serial.next_tick();
+            threaded.next_tick();
+        }
+        assert_eq!(serial.cells(), threaded.cells());
+        assert!(serial.ages().eq(threaded.ages()));
+        assert_eq!(serial.generations(), threaded.generations());
+
+        assert_eq!(World::new(8, 8).threads(), rayon::current_num_threads());
+    }
+}
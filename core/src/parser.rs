@@ -1,13 +1,43 @@
+use std::convert::TryFrom;
+
 use nom::{
-    alt, do_parse, line_ending, many0, many1, map, named, one_of, opt, space, tag,
+    alt, do_parse, line_ending, many0, many1, map, map_opt, named, one_of, opt, pair, space, tag,
     take_until_and_consume, terminated, types::CompleteStr,
 };
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum RleTag {
     NextLine,
+    /// `b`, or `.` in multi-state patterns.
     Dead,
     Alive,
+    /// A state other than 0 of a multi-state pattern, see `multi_state`.
+    State(u8),
+}
+
+/// The state written `letter` after an optional `prefix` in multi-state
+/// patterns: `A` to `X` are states 1 to 24, `pA` to `pX` 25 to 48, and so
+/// on up to `yO`, 255.
+pub(crate) fn multi_state(prefix: Option<char>, letter: char) -> Option<u8> {
+    let prefix = match prefix {
+        Some(p @ 'p'..='y') => p as u32 - 'p' as u32 + 1,
+        Some(_) => return None,
+        None => 0,
+    };
+    match letter {
+        'A'..='X' => u8::try_from(prefix * 24 + letter as u32 - 'A' as u32 + 1).ok(),
+        _ => None,
+    }
+}
+
+/// How `state`, at least 1, is written in multi-state patterns.
+pub(crate) fn multi_state_name(state: u8) -> String {
+    let (prefix, letter) = ((state - 1) / 24, (state - 1) % 24);
+    let letter = (b'A' + letter) as char;
+    match prefix {
+        0 => letter.to_string(),
+        p => format!("{}{}", (b'p' + p - 1) as char, letter),
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -29,16 +59,19 @@ pub enum RleComment {
     Name(String),
     Author(String),
     Coordinates(i32, i32),
+    /// XLife's `#r` line, holding the rule.
+    Rule(String),
     Other(String),
 }
 
 named!(
-    rle_comment<CompleteStr, RleComment>,
+    pub(crate) rle_comment<CompleteStr, RleComment>,
     alt!(
         rle_comment_coordinates |
         rle_comment_author |
         rle_comment_name |
         rle_comment_comment |
+        rle_comment_rule |
         rle_comment_other
     )
 );
@@ -86,6 +119,16 @@ named!(
     )
 );
 
+named!(
+    rle_comment_rule<CompleteStr, RleComment>,
+    do_parse!(
+        opt!(space) >>
+        tag!("#r ") >>
+        x: take_until_and_consume!("\n") >>
+        (RleComment::Rule(x.to_string()))
+    )
+);
+
 named!(
     rle_comment_name<CompleteStr, RleComment>,
     do_parse!(
@@ -132,7 +175,7 @@ named!(
 );
 
 named!(
-    rle_first_line<CompleteStr, RleFirstLine>,
+    pub(crate) rle_first_line<CompleteStr, RleFirstLine>,
     do_parse!(
         opt!(space) >>
         tag!("x") >> opt!(space) >> tag!("=") >> opt!(space) >>
@@ -199,14 +242,25 @@ mod firt_line_tests {
 // }}}
 
 named!(rle_tag_next_line<CompleteStr, RleTag>, do_parse!(tag!("$") >> (RleTag::NextLine)));
-named!(rle_tag_dead<CompleteStr, RleTag>, do_parse!(tag!("b") >> (RleTag::Dead)));
+named!(
+    rle_tag_dead<CompleteStr, RleTag>,
+    do_parse!(alt!(tag!("b") | tag!(".")) >> (RleTag::Dead))
+);
 named!(rle_tag_alive<CompleteStr, RleTag>, do_parse!(tag!("o") >> (RleTag::Alive)));
+named!(
+    rle_tag_state<CompleteStr, RleTag>,
+    map_opt!(
+        pair!(opt!(one_of!("pqrstuvwxy")), one_of!("ABCDEFGHIJKLMNOPQRSTUVWX")),
+        |(prefix, letter)| multi_state(prefix, letter).map(RleTag::State)
+    )
+);
 named!(
     rle_tag<CompleteStr, RleTag>,
     alt!(
         rle_tag_next_line |
         rle_tag_dead |
-        rle_tag_alive
+        rle_tag_alive |
+        rle_tag_state
     )
 );
 
@@ -222,12 +276,23 @@ mod tests_tag {
 
         let s = "o";
         assert_eq!(rle_tag(s.into()), Ok(("".into(), RleTag::Alive)));
+
+        let s = ".";
+        assert_eq!(rle_tag(s.into()), Ok(("".into(), RleTag::Dead)));
+
+        let expectations = vec![("A", 1), ("X", 24), ("pA", 25), ("qB", 50), ("yO", 255)];
+        for (s, state) in expectations {
+            assert_eq!(rle_tag(s.into()), Ok(("".into(), RleTag::State(state))));
+            assert_eq!(multi_state_name(state), s);
+        }
+        assert!(rle_tag("yP".into()).is_err());
+        assert!(rle_tag("pz".into()).is_err());
     }
 
     #[test]
     fn invalid_tag() {
-        (0..127u8).into_iter().for_each(|c| {
-            if c as char == 'b' || c as char == 'o' || c as char == '$' {
+        (0..127u8).for_each(|c| {
+            if "bo$.ABCDEFGHIJKLMNOPQRSTUVWX".contains(c as char) {
                 return;
             }
             let s = Some(c as char).iter().collect::<String>();
@@ -367,7 +432,7 @@ named!(
             l: rle_first_line >>
             ct: many1!(rle_tag_sequence) >>
             (Rle {
-                comments: comments,
+                comments,
                 size: l,
                 content: ct
             })
@@ -400,11 +465,12 @@ mod tests {
             Ok(())
         }
 
-        visit_dirs(Path::new("patterns"), |path| {
+        let patterns = Path::new(env!("CARGO_MANIFEST_DIR")).join("../patterns");
+        visit_dirs(&patterns, |path| {
             let mut f = fs::File::open(path).unwrap();
             let mut s = String::new();
             f.read_to_string(&mut s)
-                .expect(&format!("Unable to read file {:?}", path));
+                .unwrap_or_else(|_| panic!("Unable to read file {:?}", path));
             if let Err(e) = parse_rle(s.as_str().into()) {
                 panic!("Failed to parse {:?}: {:?}", path, e);
             }
@@ -0,0 +1,381 @@
+//! Predecessor search: finding a parent of the pattern on the board, or
+//! proving it has none within a window, i.e. that it is a Garden of Eden
+//! there.
+//!
+//! The parent is looked for in the bounding box of the pattern grown by a
+//! margin. Every cell of the window is dead or alive, and the cells of the
+//! window grown by one must all evolve into the pattern: the search
+//! backtracks over the window cells, in rows or columns along its short
+//! side, and gives up on a partial parent as soon as one of these cells
+//! can't evolve right whatever the cells left undecided.
+//!
+//! On a plane the window stops at the edges of the board, past which cells
+//! stay dead whatever their neighbors. On a torus it may cross the edges, as
+//! long as it doesn't meet itself on the other side.
+
+use std::fmt;
+
+use super::{Cell, Error, Rect, Result, Topology, World};
+
+const DEAD: u8 = 0;
+const ALIVE: u8 = 1;
+const UNKNOWN: u8 = 2;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Predecessor {
+    /// A parent, as RLE placed on the board like `export_rle` does.
+    Found(String),
+    /// No parent fits in the window.
+    NotFound,
+    /// The search stopped after trying its maximum number of cells.
+    GaveUp,
+}
+
+impl fmt::Display for Predecessor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Predecessor::Found(rle) => write!(f, "{}", rle),
+            Predecessor::NotFound => write!(f, "no predecessor found"),
+            Predecessor::GaveUp => write!(f, "search gave up"),
+        }
+    }
+}
+
+struct Search {
+    /// Neighbor counts giving birth and survival, as bit masks.
+    birth: u16,
+    survival: u16,
+    /// The window, in `DEAD`, `ALIVE` or `UNKNOWN` cells.
+    width: i32,
+    height: i32,
+    cells: Vec<u8>,
+    /// The pattern over the window grown by one cell on each side, `None`
+    /// past the edges of a plane.
+    target: Vec<Option<bool>>,
+    order: Vec<usize>,
+    nodes: u64,
+    max_nodes: u64,
+}
+
+impl Search {
+    fn cell(&self, row: i32, col: i32) -> u8 {
+        if row < 0 || row >= self.height || col < 0 || col >= self.width {
+            DEAD
+        } else {
+            self.cells[(row * self.width + col) as usize]
+        }
+    }
+
+    /// Whether the target cell at `(row, col)`, one cell up and left from
+    /// window coordinates, can still evolve into the pattern.
+    fn feasible(&self, row: i32, col: i32) -> bool {
+        let (mut alive, mut unknown) = (0, 0);
+        let mut center = DEAD;
+        for r in -1..=1 {
+            for c in -1..=1 {
+                let cell = self.cell(row + r - 1, col + c - 1);
+                match (r, c, cell) {
+                    (0, 0, _) => center = cell,
+                    (_, _, ALIVE) => alive += 1,
+                    (_, _, UNKNOWN) => unknown += 1,
+                    _ => {}
+                }
+            }
+        }
+        let alive_next = match self.target[(row * (self.width + 2) + col) as usize] {
+            Some(alive_next) => alive_next,
+            None => return true,
+        };
+        let counts = ((1u16 << (unknown + 1)) - 1) << alive;
+        let reachable = |rule: u16| {
+            if alive_next {
+                rule & counts != 0
+            } else {
+                !rule & counts != 0
+            }
+        };
+        match center {
+            DEAD => reachable(self.birth),
+            ALIVE => reachable(self.survival),
+            _ => reachable(self.birth) || reachable(self.survival),
+        }
+    }
+
+    /// Whether every target cell around the window cell `idx` is feasible.
+    fn consistent(&self, idx: usize) -> bool {
+        let (row, col) = (idx as i32 / self.width, idx as i32 % self.width);
+        (0..3).all(|r| (0..3).all(|c| self.feasible(row + r, col + c)))
+    }
+
+    /// Decides the cells in `order`: `Some(true)` when they make a parent,
+    /// `Some(false)` when none does, `None` when out of nodes.
+    ///
+    /// The cells are their own backtracking stack: the `k`-th one is tried
+    /// dead then alive, and set back to unknown when both failed, so that
+    /// deep windows don't need deep recursion.
+    fn solve(&mut self) -> Option<bool> {
+        let mut k = 0;
+        while let Some(&idx) = self.order.get(k) {
+            let cell = match self.cells[idx] {
+                UNKNOWN => DEAD,
+                DEAD => ALIVE,
+                _ => {
+                    self.cells[idx] = UNKNOWN;
+                    if k == 0 {
+                        return Some(false);
+                    }
+                    k -= 1;
+                    continue;
+                }
+            };
+            self.nodes += 1;
+            if self.nodes > self.max_nodes {
+                return None;
+            }
+            self.cells[idx] = cell;
+            if self.consistent(idx) {
+                k += 1;
+            }
+        }
+        Some(true)
+    }
+}
+
+impl World {
+    /// Looks for a parent of the pattern under the world's rule, made of
+    /// cells at most `margin` cells away from its bounding box, trying at
+    /// most `max_nodes` cell values.
+    pub fn find_predecessor(&self, margin: u32, max_nodes: u64) -> Result<Predecessor> {
+        if self.automaton.is_some() {
+            return Err(Error::Search(
+                "predecessors are only searched under Life-like rules".to_string(),
+            ));
+        }
+        // Far from any parent, cells see no neighbors: they would be born.
+        if self.rule.born(0) {
+            return Ok(Predecessor::NotFound);
+        }
+        let bounds = match self.pattern_boundaries() {
+            Some(bounds) => bounds,
+            None => return Ok(Predecessor::Found(String::new())),
+        };
+
+        let margin = margin as i32;
+        let mut window = Rect {
+            x: bounds.x as i32 - margin,
+            y: bounds.y as i32 - margin,
+            width: bounds.width as i32 + 2 * margin,
+            height: bounds.height as i32 + 2 * margin,
+        };
+        let on_board = |row: i32, col: i32| {
+            self.topology == Topology::Torus
+                || (row >= 0 && row < self.height && col >= 0 && col < self.width)
+        };
+        match self.topology {
+            Topology::Plane => {
+                let (right, bottom) = (window.x + window.width, window.y + window.height);
+                window.x = window.x.max(0);
+                window.y = window.y.max(0);
+                window.width = right.min(self.width) - window.x;
+                window.height = bottom.min(self.height) - window.y;
+            }
+            // The cells the window can reach must not wrap onto it.
+            Topology::Torus => {
+                if window.width + 2 > self.width || window.height + 2 > self.height {
+                    return Err(Error::Search(format!(
+                        "a window of {}x{} cells doesn't fit on the torus",
+                        window.width, window.height
+                    )));
+                }
+            }
+        }
+        let in_bounds = |row: i32, col: i32| {
+            row >= bounds.y as i32
+                && row < (bounds.y + bounds.height) as i32
+                && col >= bounds.x as i32
+                && col < (bounds.x + bounds.width) as i32
+        };
+        let mut target = Vec::new();
+        for row in window.y - 1..window.y + window.height + 1 {
+            for col in window.x - 1..window.x + window.width + 1 {
+                target.push(if on_board(row, col) {
+                    Some(in_bounds(row, col) && self.cell((row, col)) == Cell::Alive)
+                } else {
+                    None
+                });
+            }
+        }
+        let mask = |f: &dyn Fn(u8) -> bool| (0..=8).filter(|&n| f(n)).fold(0, |m, n| m | 1 << n);
+
+        let (width, height) = (window.width, window.height);
+        let order = if width <= height {
+            (0..(width * height) as usize).collect()
+        } else {
+            (0..width)
+                .flat_map(|col| (0..height).map(move |row| (row * width + col) as usize))
+                .collect()
+        };
+        let mut search = Search {
+            birth: mask(&|n| self.rule.born(n)),
+            survival: mask(&|n| self.rule.survives(n)),
+            width,
+            height,
+            cells: vec![UNKNOWN; (width * height) as usize],
+            target,
+            order,
+            nodes: 0,
+            max_nodes,
+        };
+        match search.solve() {
+            Some(true) => {}
+            Some(false) => return Ok(Predecessor::NotFound),
+            None => return Ok(Predecessor::GaveUp),
+        }
+
+        let mut parent = World::new(self.width, self.height);
+        parent.rule = self.rule;
+        parent.topology = self.topology;
+        for (idx, &cell) in search.cells.iter().enumerate() {
+            if cell == ALIVE {
+                let (row, col) = (idx as i32 / width, idx as i32 % width);
+                parent.put_cell((window.y + row, window.x + col), Cell::Alive);
+            }
+        }
+        Ok(Predecessor::Found(parent.export_rle()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(rule: &str, rle: &str) -> World {
+        let mut world = World::new(32, 32);
+        world.set_rule(rule.parse().unwrap());
+        world.load_rle_str(rle).unwrap();
+        world
+    }
+
+    /// Checks that `predecessor` evolves into the pattern of `world`.
+    fn assert_parent(world: &World, predecessor: &Predecessor) {
+        let rle = match predecessor {
+            Predecessor::Found(rle) => rle,
+            other => panic!("no parent: {}", other),
+        };
+        let mut parent = World::new(world.width(), world.height());
+        parent.set_rule(world.rule());
+        parent.set_topology(world.topology());
+        parent.load_rle_str(rle).unwrap();
+        parent.next_tick();
+        assert_eq!(parent.cells(), world.cells());
+    }
+
+    #[test]
+    fn found_test() {
+        let patterns = [
+            ("B3/S23", "x = 3, y = 3\nbo$2bo$3o!"),
+            ("B3/S23", "x = 3, y = 1\n3o!"),
+            ("B3/S23", "x = 4, y = 3\nb2o$o2bo$b2o!"),
+            ("B36/S23", "x = 2, y = 2\n2o$2o!"),
+        ];
+        for &(rule, rle) in patterns.iter() {
+            let world = board(rule, rle);
+            assert_parent(&world, &world.find_predecessor(1, 1_000_000).unwrap());
+        }
+
+        let empty = World::new(8, 8);
+        assert_eq!(
+            empty.find_predecessor(1, 10),
+            Ok(Predecessor::Found(String::new()))
+        );
+    }
+
+    #[test]
+    fn garden_of_eden_test() {
+        // Under B8/S, alive cells are born surrounded by cells that die, so
+        // two of them can't be neighbors.
+        let world = board("B8/S", "x = 2, y = 1\n2o!");
+        assert_eq!(
+            world.find_predecessor(2, 1_000_000),
+            Ok(Predecessor::NotFound)
+        );
+
+        // While a single cell comes from the 8 around it.
+        let world = board("B8/S", "x = 1, y = 1\no!");
+        assert_parent(&world, &world.find_predecessor(1, 1_000).unwrap());
+    }
+
+    #[test]
+    fn sparse_window_test() {
+        // Two blinkers far apart make a window of tens of thousands of
+        // cells, searched on a thread with a small stack.
+        let mut world = World::new(400, 400);
+        for &at in &[100, 290] {
+            for row in at..at + 3 {
+                world.set_cell((row, at + 1), Cell::Alive);
+            }
+        }
+        let search = std::thread::Builder::new()
+            .stack_size(1024 * 1024)
+            .spawn(move || {
+                let predecessor = world.find_predecessor(1, 10_000_000).unwrap();
+                assert_parent(&world, &predecessor);
+            })
+            .unwrap();
+        search.join().unwrap();
+    }
+
+    /// A 16 by 16 or 10 by 10 board with the given cells alive.
+    fn edge_board(size: i32, topology: Topology, cells: &[(i32, i32)]) -> World {
+        let mut world = World::new(size, size);
+        world.set_topology(topology);
+        for &at in cells {
+            world.set_cell(at, Cell::Alive);
+        }
+        world
+    }
+
+    #[test]
+    fn edges_test() {
+        // Patterns touching the edges of a plane have parents inside it.
+        let blinker = [(0, 0), (1, 0), (2, 0)];
+        let block = [
+            (0, 0),
+            (0, 1),
+            (0, 2),
+            (1, 0),
+            (1, 1),
+            (1, 2),
+            (2, 0),
+            (2, 1),
+            (2, 2),
+        ];
+        let glider = [(13, 14), (14, 15), (15, 13), (15, 14), (15, 15)];
+        for cells in &[&blinker[..], &block[..], &glider[..]] {
+            let world = edge_board(16, Topology::Plane, cells);
+            assert_parent(&world, &world.find_predecessor(2, 1_000_000).unwrap());
+        }
+
+        // On a torus the window may cross the edges, but not meet itself.
+        let glider = [(7, 8), (8, 9), (9, 7), (9, 8), (9, 9)];
+        let world = edge_board(10, Topology::Torus, &glider);
+        assert_parent(&world, &world.find_predecessor(1, 1_000_000).unwrap());
+        assert!(world.find_predecessor(3, 1_000_000).is_err());
+        let across = [(8, 9), (9, 0), (0, 8), (0, 9), (0, 0)];
+        let world = edge_board(10, Topology::Torus, &across);
+        assert!(world.find_predecessor(1, 1_000_000).is_err());
+    }
+
+    #[test]
+    fn limits_test() {
+        let world = board("B3/S23", "x = 3, y = 3\nbo$2bo$3o!");
+        assert_eq!(world.find_predecessor(1, 3), Ok(Predecessor::GaveUp));
+
+        let world = board("B03/S23", "x = 1, y = 1\no!");
+        assert_eq!(world.find_predecessor(1, 100), Ok(Predecessor::NotFound));
+
+        let mut world = World::new(8, 8);
+        world.set_automaton(Some(crate::Automaton::WireWorld));
+        assert!(world.find_predecessor(1, 100).is_err());
+    }
+}
@@ -0,0 +1,419 @@
+use super::{Cell, Coord, World};
+
+type Color = [u8; 4];
+
+/// Number of generations over which the heat map fades from the "young"
+/// colors to the regular alive and dead colors.
+const HEAT_SPAN: u32 = 32;
+
+fn rgb(color: u32) -> Color {
+    [(color >> 16) as u8, (color >> 8) as u8, color as u8, 255]
+}
+
+fn blend(from: Color, to: Color, step: u32, steps: u32) -> Color {
+    let step = ::std::cmp::min(step, steps);
+    let mut color = to;
+    for i in 0..3 {
+        let from = i32::from(from[i]);
+        let to = i32::from(to[i]);
+        color[i] = (from + (to - from) * step as i32 / steps as i32) as u8;
+    }
+    color
+}
+
+/// Draws a `World` into an RGBA framebuffer, which a browser can blit to a
+/// canvas with a single `putImageData` call.
+///
+/// The viewport is expressed in cells: `offset_row`/`offset_col` is the world
+/// cell drawn in the top-left corner and `cell_size` is the zoom level, in
+/// pixels per cell. The viewport wraps around the torus like the world does.
+pub struct Renderer {
+    width: u32,
+    height: u32,
+    cell_size: u32,
+    grid: bool,
+    alive_color: Color,
+    dead_color: Color,
+    grid_color: Color,
+    color_by_age: bool,
+    newborn_color: Color,
+    dying_color: Color,
+    palette: Vec<Color>,
+    ant_color: Color,
+    offset_row: i32,
+    offset_col: i32,
+    world_size: (i32, i32),
+    needs_full_redraw: bool,
+    pixels: Vec<u8>,
+}
+
+impl Renderer {
+    pub fn new(width: u32, height: u32, cell_size: u32) -> Renderer {
+        Renderer {
+            width,
+            height,
+            cell_size: ::std::cmp::max(cell_size, 1),
+            grid: true,
+            alive_color: rgb(0x00_00_00),
+            dead_color: rgb(0xFF_FF_FF),
+            grid_color: rgb(0xDD_DD_DD),
+            color_by_age: false,
+            newborn_color: rgb(0xFF_40_00),
+            dying_color: rgb(0x80_A0_FF),
+            palette: Vec::new(),
+            ant_color: rgb(0xE0_20_20),
+            offset_row: 0,
+            offset_col: 0,
+            world_size: (0, 0),
+            needs_full_redraw: true,
+            pixels: vec![0; (width * height * 4) as usize],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        self.pixels = vec![0; (width * height * 4) as usize];
+        self.needs_full_redraw = true;
+    }
+
+    pub fn cell_size(&self) -> u32 {
+        self.cell_size
+    }
+
+    pub fn set_cell_size(&mut self, cell_size: u32) {
+        let cell_size = ::std::cmp::max(cell_size, 1);
+        if cell_size != self.cell_size {
+            self.cell_size = cell_size;
+            self.needs_full_redraw = true;
+        }
+    }
+
+    pub fn set_grid(&mut self, grid: bool) {
+        if grid != self.grid {
+            self.grid = grid;
+            self.needs_full_redraw = true;
+        }
+    }
+
+    /// Colors are given as `0xRRGGBB`.
+    pub fn set_alive_color(&mut self, color: u32) {
+        self.alive_color = rgb(color);
+        self.needs_full_redraw = true;
+    }
+
+    pub fn set_dead_color(&mut self, color: u32) {
+        self.dead_color = rgb(color);
+        self.needs_full_redraw = true;
+    }
+
+    pub fn set_grid_color(&mut self, color: u32) {
+        self.grid_color = rgb(color);
+        self.needs_full_redraw = true;
+    }
+
+    /// Colors alive cells from `newborn_color` to the alive color as they
+    /// age, and leaves a `dying_color` trail fading into the dead color behind
+    /// dead cells. Needs age tracking enabled on the rendered world, and
+    /// redraws the whole framebuffer on every frame.
+    pub fn set_color_by_age(&mut self, enabled: bool) {
+        self.color_by_age = enabled;
+        self.needs_full_redraw = true;
+    }
+
+    pub fn set_newborn_color(&mut self, color: u32) {
+        self.newborn_color = rgb(color);
+        self.needs_full_redraw = true;
+    }
+
+    pub fn set_dying_color(&mut self, color: u32) {
+        self.dying_color = rgb(color);
+        self.needs_full_redraw = true;
+    }
+
+    /// Colors of the states of many-state automata, from state 0 on. States
+    /// past the palette get the color their automaton gives them, or else
+    /// the dead color for state 0 and the alive color for the others.
+    pub fn set_palette(&mut self, colors: &[u32]) {
+        self.palette = colors.iter().map(|c| rgb(*c)).collect();
+        self.needs_full_redraw = true;
+    }
+
+    /// Color of the cells under an ant.
+    pub fn set_ant_color(&mut self, color: u32) {
+        self.ant_color = rgb(color);
+        self.needs_full_redraw = true;
+    }
+
+    pub fn set_viewport(&mut self, row: i32, col: i32) {
+        self.offset_row = row;
+        self.offset_col = col;
+        self.needs_full_redraw = true;
+    }
+
+    pub fn pan(&mut self, rows: i32, cols: i32) {
+        self.set_viewport(self.offset_row + rows, self.offset_col + cols);
+    }
+
+    /// Converts a canvas position in pixels to the world cell drawn there.
+    pub fn cell_at(&self, world: &World, x: u32, y: u32) -> Coord {
+        let row = self.offset_row + (y / self.cell_size) as i32;
+        let col = self.offset_col + (x / self.cell_size) as i32;
+        Coord::new(row.rem_euclid(world.height), col.rem_euclid(world.width))
+    }
+
+    pub fn invalidate(&mut self) {
+        self.needs_full_redraw = true;
+    }
+
+    /// Redraws the framebuffer. Only the world's `changed_cells` are painted
+    /// unless something invalidated the whole picture since the last call.
+    ///
+    /// The world's changed cells are left untouched: callers are expected to
+    /// call `World::reset_changed_cells` once the frame has been presented.
+    pub fn render(&mut self, world: &World) {
+        if self.world_size != (world.width, world.height) {
+            self.world_size = (world.width, world.height);
+            self.needs_full_redraw = true;
+        }
+
+        if self.needs_full_redraw || self.color_by_age {
+            self.draw_all(world);
+            self.needs_full_redraw = false;
+        } else {
+            world
+                .changed_cells
+                .iter()
+                .for_each(|idx| self.draw_cell(world, *idx));
+        }
+    }
+
+    /// The RGBA pixels, row by row.
+    pub fn framebuffer(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    fn visible_rows(&self) -> i32 {
+        self.height.div_ceil(self.cell_size) as i32
+    }
+
+    fn visible_cols(&self) -> i32 {
+        self.width.div_ceil(self.cell_size) as i32
+    }
+
+    fn draw_all(&mut self, world: &World) {
+        for screen_row in 0..self.visible_rows() {
+            for screen_col in 0..self.visible_cols() {
+                let color = self.cell_color(
+                    world,
+                    self.offset_row + screen_row,
+                    self.offset_col + screen_col,
+                );
+                self.fill_cell(screen_row, screen_col, color);
+            }
+        }
+    }
+
+    /// Paints every on-screen copy of the cell at `idx`: when the viewport is
+    /// larger than the world, the torus is tiled and a cell shows up more
+    /// than once.
+    fn draw_cell(&mut self, world: &World, idx: i32) {
+        let (row, col) = world.to_coords(idx);
+        let color = self.cell_color(world, row, col);
+
+        let first_row = (row - self.offset_row).rem_euclid(world.height);
+        let first_col = (col - self.offset_col).rem_euclid(world.width);
+
+        let mut screen_row = first_row;
+        while screen_row < self.visible_rows() {
+            let mut screen_col = first_col;
+            while screen_col < self.visible_cols() {
+                self.fill_cell(screen_row, screen_col, color);
+                screen_col += world.width;
+            }
+            screen_row += world.height;
+        }
+    }
+
+    fn cell_color(&self, world: &World, row: i32, col: i32) -> Color {
+        if world
+            .ants()
+            .iter()
+            .any(|ant| ant.position() == Coord::new(row, col))
+        {
+            return self.ant_color;
+        }
+        if let Some(automaton) = world.automaton() {
+            let state = world.state((row, col));
+            return match self.palette.get(state as usize) {
+                Some(color) => *color,
+                None => match automaton.color(state) {
+                    Some(color) => rgb(color),
+                    None if state == 0 => self.dead_color,
+                    None => self.alive_color,
+                },
+            };
+        }
+
+        let cell = world.get(row, col);
+        if !self.color_by_age {
+            return match cell {
+                Cell::Alive => self.alive_color,
+                Cell::Dead => self.dead_color,
+            };
+        }
+        match (cell, world.age((row, col))) {
+            (Cell::Alive, age) => blend(
+                self.newborn_color,
+                self.alive_color,
+                age.unwrap_or(HEAT_SPAN),
+                HEAT_SPAN,
+            ),
+            (Cell::Dead, Some(age)) => blend(self.dying_color, self.dead_color, age, HEAT_SPAN),
+            (Cell::Dead, None) => self.dead_color,
+        }
+    }
+
+    fn fill_cell(&mut self, screen_row: i32, screen_col: i32, color: Color) {
+        let grid = self.grid && self.cell_size > 2;
+
+        let top = screen_row as u32 * self.cell_size;
+        let left = screen_col as u32 * self.cell_size;
+        let bottom = ::std::cmp::min(top + self.cell_size, self.height);
+        let right = ::std::cmp::min(left + self.cell_size, self.width);
+
+        for y in top..bottom {
+            for x in left..right {
+                let on_grid = grid && (y == top || x == left);
+                let offset = ((y * self.width + x) * 4) as usize;
+                self.pixels[offset..offset + 4].copy_from_slice(if on_grid {
+                    &self.grid_color
+                } else {
+                    &color
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(renderer: &Renderer, x: u32, y: u32) -> Color {
+        let offset = ((y * renderer.width + x) * 4) as usize;
+        let mut color = [0; 4];
+        color.copy_from_slice(&renderer.pixels[offset..offset + 4]);
+        color
+    }
+
+    #[test]
+    fn full_redraw_test() {
+        let mut world = World::new(4, 4);
+        world.set_cell((1, 2), Cell::Alive);
+
+        let mut renderer = Renderer::new(16, 16, 4);
+        renderer.render(&world);
+
+        assert_eq!(pixel(&renderer, 9, 5), rgb(0x00_00_00));
+        assert_eq!(pixel(&renderer, 8, 4), rgb(0xDD_DD_DD));
+        assert_eq!(pixel(&renderer, 1, 1), rgb(0xFF_FF_FF));
+    }
+
+    #[test]
+    fn partial_redraw_test() {
+        let mut world = World::new(4, 4);
+        let mut renderer = Renderer::new(16, 16, 4);
+        renderer.set_grid(false);
+        renderer.render(&world);
+        world.reset_changed_cells();
+
+        world.toggle((3, 3));
+        renderer.render(&world);
+        assert_eq!(pixel(&renderer, 12, 12), rgb(0x00_00_00));
+        assert_eq!(pixel(&renderer, 15, 15), rgb(0x00_00_00));
+        assert_eq!(pixel(&renderer, 11, 11), rgb(0xFF_FF_FF));
+    }
+
+    #[test]
+    fn viewport_wraps_test() {
+        let mut world = World::new(4, 4);
+        world.set_cell((0, 0), Cell::Alive);
+
+        let mut renderer = Renderer::new(8, 8, 1);
+        renderer.set_viewport(-1, -1);
+        renderer.render(&world);
+
+        assert_eq!(pixel(&renderer, 1, 1), rgb(0x00_00_00));
+        assert_eq!(pixel(&renderer, 5, 5), rgb(0x00_00_00));
+        assert_eq!(pixel(&renderer, 0, 0), rgb(0xFF_FF_FF));
+        assert_eq!(renderer.cell_at(&world, 1, 1), Coord::new(0, 0));
+    }
+
+    #[test]
+    fn color_by_age_test() {
+        let mut world = World::new(4, 4);
+        world.set_age_tracking(true);
+        world.set_cell((1, 1), Cell::Alive);
+
+        let mut renderer = Renderer::new(4, 4, 1);
+        renderer.set_color_by_age(true);
+        renderer.render(&world);
+        assert_eq!(pixel(&renderer, 1, 1), rgb(0xFF_40_00));
+        assert_eq!(pixel(&renderer, 0, 0), rgb(0xFF_FF_FF));
+
+        world.next_tick();
+        renderer.render(&world);
+        assert_eq!(pixel(&renderer, 1, 1), rgb(0x80_A0_FF));
+    }
+
+    #[test]
+    fn palette_test() {
+        let mut world = World::new(4, 1);
+        world
+            .load_rule_table("@RULE three\n@TABLE\nn_states:3\n@COLORS\n1 255 0 0\n2 0 0 255\n")
+            .unwrap();
+        world.set_state((0, 1), 1);
+        world.set_state((0, 2), 2);
+
+        let mut renderer = Renderer::new(4, 1, 1);
+        renderer.render(&world);
+        assert_eq!(pixel(&renderer, 0, 0), rgb(0xFF_FF_FF));
+        assert_eq!(pixel(&renderer, 1, 0), rgb(0xFF_00_00));
+        assert_eq!(pixel(&renderer, 2, 0), rgb(0x00_00_FF));
+
+        renderer.set_palette(&[0x10_10_10, 0x20_20_20]);
+        renderer.render(&world);
+        assert_eq!(pixel(&renderer, 0, 0), rgb(0x10_10_10));
+        assert_eq!(pixel(&renderer, 1, 0), rgb(0x20_20_20));
+        assert_eq!(pixel(&renderer, 2, 0), rgb(0x00_00_FF));
+    }
+
+    #[test]
+    fn ants_test() {
+        let mut world = World::new(4, 1);
+        world.set_rule("B/S012345678".parse().unwrap());
+        let ant = crate::Turmite::langtons_ant();
+        world.add_ant(ant, (0, 1), crate::Direction::North).unwrap();
+
+        let mut renderer = Renderer::new(4, 1, 1);
+        renderer.set_ant_color(0x00_FF_00);
+        renderer.render(&world);
+        assert_eq!(pixel(&renderer, 1, 0), rgb(0x00_FF_00));
+
+        // Partial redraws follow the ant.
+        world.reset_changed_cells();
+        world.next_tick();
+        renderer.render(&world);
+        assert_eq!(pixel(&renderer, 1, 0), rgb(0x00_00_00));
+        assert_eq!(pixel(&renderer, 2, 0), rgb(0x00_FF_00));
+    }
+}
@@ -0,0 +1,145 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::Error;
+
+/// A Life-like (outer totalistic) rule: which neighbor counts give birth to a
+/// dead cell and which ones keep an alive cell alive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rule {
+    birth: [bool; 9],
+    survival: [bool; 9],
+}
+
+impl Rule {
+    pub fn life() -> Rule {
+        "B3/S23".parse().unwrap()
+    }
+
+    #[inline(always)]
+    pub fn born(&self, neighbors: u8) -> bool {
+        self.birth[neighbors as usize]
+    }
+
+    #[inline(always)]
+    pub fn survives(&self, neighbors: u8) -> bool {
+        self.survival[neighbors as usize]
+    }
+}
+
+impl Default for Rule {
+    fn default() -> Rule {
+        Rule::life()
+    }
+}
+
+fn parse_counts(s: &str, rule: &str) -> Result<[bool; 9], Error> {
+    let mut counts = [false; 9];
+    for c in s.chars() {
+        match c.to_digit(10) {
+            Some(n) if n <= 8 => counts[n as usize] = true,
+            _ => return Err(Error::InvalidRule(rule.to_string())),
+        }
+    }
+    Ok(counts)
+}
+
+/// Accepts the `B3/S23` notation (case insensitive, in any order) as well as
+/// the older `23/3` survival/birth notation.
+impl FromStr for Rule {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.trim().split('/').collect();
+        if parts.len() != 2 {
+            return Err(Error::InvalidRule(s.to_string()));
+        }
+
+        let mut birth = None;
+        let mut survival = None;
+        for part in parts.iter() {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some('B') | Some('b') => birth = Some(parse_counts(chars.as_str(), s)?),
+                Some('S') | Some('s') => survival = Some(parse_counts(chars.as_str(), s)?),
+                _ => (),
+            }
+        }
+
+        match (birth, survival) {
+            (Some(birth), Some(survival)) => Ok(Rule { birth, survival }),
+            (None, None) => Ok(Rule {
+                survival: parse_counts(parts[0], s)?,
+                birth: parse_counts(parts[1], s)?,
+            }),
+            _ => Err(Error::InvalidRule(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "B")?;
+        for n in (0..9).filter(|n| self.birth[*n]) {
+            write!(f, "{}", n)?;
+        }
+        write!(f, "/S")?;
+        for n in (0..9).filter(|n| self.survival[*n]) {
+            write!(f, "{}", n)?;
+        }
+        Ok(())
+    }
+}
+
+/// How the edges of the board behave.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Topology {
+    /// Opposite edges are glued together.
+    #[default]
+    Torus,
+    /// Cells outside the board are dead forever.
+    Plane,
+}
+
+impl FromStr for Topology {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "torus" => Ok(Topology::Torus),
+            "plane" => Ok(Topology::Plane),
+            _ => Err(Error::UnknownTopology(s.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_rule_test() {
+        let life = Rule::life();
+        assert_eq!("b3/s23".parse(), Ok(life));
+        assert_eq!("S23/B3".parse(), Ok(life));
+        assert_eq!("23/3".parse(), Ok(life));
+        assert_eq!(life.to_string(), "B3/S23");
+
+        let highlife: Rule = "B36/S23".parse().unwrap();
+        assert!(highlife.born(6));
+        assert!(!highlife.survives(6));
+        assert_eq!(
+            "/3".parse::<Rule>().map(|r| r.to_string()),
+            Ok("B3/S".to_string())
+        );
+    }
+
+    #[test]
+    fn invalid_rule_test() {
+        assert!("B9/S23".parse::<Rule>().is_err());
+        assert!("B3".parse::<Rule>().is_err());
+        assert!("B3/23".parse::<Rule>().is_err());
+        assert!("Bx/S2".parse::<Rule>().is_err());
+    }
+}
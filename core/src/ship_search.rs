@@ -0,0 +1,641 @@
+//! Spaceship and oscillator search, in the spirit of lifesrc.
+//!
+//! The search looks at every generation of one period of a pattern at once,
+//! inside a box. Each cell of each generation is dead, alive or undecided:
+//! every generation must evolve into the next one, and the last one into the
+//! first moved by the displacement, while cells outside the box stay dead.
+//! The search backtracks over the undecided cells, all generations of a
+//! position in a row, rows or columns along the short side of the box. After
+//! each decision it fills in the cells that a single value keeps consistent,
+//! and gives up on the branch as soon as a cell can't evolve right.
+//!
+//! Complete patterns are then run with `next_tick` to check that they come
+//! back after the period and no sooner, and reported once per object.
+
+use std::collections::HashSet;
+
+use super::{apgcode, Cell, Coord, DrawSymmetry, Error, Result, Topology, World};
+
+const DEAD: u8 = 0;
+const ALIVE: u8 = 1;
+const UNKNOWN: u8 = 2;
+
+/// What to look for, and for how long.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShipSearch {
+    pub period: u32,
+    /// Rows and columns the pattern moves by in a period, `(0, 0)` for
+    /// oscillators.
+    pub displacement: (i32, i32),
+    /// Size of the box every generation fits in.
+    pub width: u32,
+    pub height: u32,
+    /// Symmetry of the pattern around the center of the box: the axes go
+    /// through cells for odd sizes, between them for even ones.
+    pub symmetry: DrawSymmetry,
+    /// Cell values tried before giving up.
+    pub max_nodes: u64,
+    /// Reports a partial result every this many nodes, never when 0.
+    pub report_every: u64,
+}
+
+impl ShipSearch {
+    pub fn new(period: u32, displacement: (i32, i32), width: u32, height: u32) -> ShipSearch {
+        ShipSearch {
+            period,
+            displacement,
+            width,
+            height,
+            symmetry: DrawSymmetry::None,
+            max_nodes: 1_000_000,
+            report_every: 0,
+        }
+    }
+}
+
+/// Results streamed by `World::search_ships`, as RLE placed on the board
+/// like `export_rle` does.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SearchEvent {
+    /// The alive cells decided so far in the first generation.
+    Partial(String),
+    /// An oscillator or spaceship of exactly the period, not reported yet.
+    Found(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchEnd {
+    /// Every pattern fitting in the box was tried.
+    Exhausted,
+    /// The caller asked to stop.
+    Stopped,
+    /// The search tried its maximum number of nodes.
+    GaveUp,
+}
+
+type Report<'a> = dyn FnMut(&Search, bool) -> bool + 'a;
+
+struct Search {
+    /// Neighbor counts giving birth and survival, as bit masks.
+    birth: u16,
+    survival: u16,
+    period: i32,
+    width: i32,
+    height: i32,
+    displacement: (i32, i32),
+    /// The generations one after the other, in `DEAD`, `ALIVE` or `UNKNOWN`
+    /// cells.
+    cells: Vec<u8>,
+    /// The cells the symmetry ties to each cell, itself included.
+    orbits: Vec<Vec<usize>>,
+    order: Vec<usize>,
+    /// Cells decided so far, to undo the decisions when backtracking.
+    trail: Vec<usize>,
+    /// Constraints to check, numbered by `constraint`.
+    queue: Vec<usize>,
+    queued: Vec<bool>,
+    nodes: u64,
+    max_nodes: u64,
+    report_every: u64,
+}
+
+impl Search {
+    fn var(&self, gen: i32, row: i32, col: i32) -> Option<usize> {
+        let (gen, row, col) = if gen == self.period {
+            (0, row - self.displacement.0, col - self.displacement.1)
+        } else {
+            (gen, row, col)
+        };
+        if row < 0 || row >= self.height || col < 0 || col >= self.width {
+            None
+        } else {
+            Some(((gen * self.height + row) * self.width + col) as usize)
+        }
+    }
+
+    fn coords(&self, var: usize) -> (i32, i32, i32) {
+        let var = var as i32;
+        let (gen, cell) = (
+            var / (self.width * self.height),
+            var % (self.width * self.height),
+        );
+        (gen, cell / self.width, cell % self.width)
+    }
+
+    fn get(&self, gen: i32, row: i32, col: i32) -> u8 {
+        self.var(gen, row, col).map_or(DEAD, |var| self.cells[var])
+    }
+
+    /// The constraint that the cell at `(row, col)` of `gen`, in the box
+    /// grown by one, evolves into the same cell of the next generation.
+    fn constraint(&self, gen: i32, row: i32, col: i32) -> Option<usize> {
+        if row < -1 || row > self.height || col < -1 || col > self.width {
+            None
+        } else {
+            Some(((gen * (self.height + 2) + row + 1) * (self.width + 2) + col + 1) as usize)
+        }
+    }
+
+    fn constraint_coords(&self, constraint: usize) -> (i32, i32, i32) {
+        let constraint = constraint as i32;
+        let size = (self.width + 2) * (self.height + 2);
+        let (gen, cell) = (constraint / size, constraint % size);
+        (
+            gen,
+            cell / (self.width + 2) - 1,
+            cell % (self.width + 2) - 1,
+        )
+    }
+
+    /// The neighborhood a constraint reads, then the cell it evolves into.
+    fn members(&self, constraint: usize) -> [Option<usize>; 10] {
+        let (gen, row, col) = self.constraint_coords(constraint);
+        let mut members = [None; 10];
+        for (i, member) in members.iter_mut().take(9).enumerate() {
+            *member = self.var(gen, row + i as i32 / 3 - 1, col + i as i32 % 3 - 1);
+        }
+        members[9] = self.var(gen + 1, row, col);
+        members
+    }
+
+    /// Whether some values of the undecided cells satisfy the constraint.
+    fn feasible(&self, constraint: usize) -> bool {
+        let (gen, row, col) = self.constraint_coords(constraint);
+        let (mut alive, mut unknown) = (0, 0);
+        let mut center = DEAD;
+        for r in -1..=1 {
+            for c in -1..=1 {
+                let cell = self.get(gen, row + r, col + c);
+                match (r, c, cell) {
+                    (0, 0, _) => center = cell,
+                    (_, _, ALIVE) => alive += 1,
+                    (_, _, UNKNOWN) => unknown += 1,
+                    _ => {}
+                }
+            }
+        }
+        let counts = ((1u16 << (unknown + 1)) - 1) << alive;
+        let next = self.get(gen + 1, row, col);
+        let reachable = |rule: u16| match next {
+            ALIVE => rule & counts != 0,
+            DEAD => !rule & counts != 0,
+            _ => true,
+        };
+        match center {
+            DEAD => reachable(self.birth),
+            ALIVE => reachable(self.survival),
+            _ => reachable(self.birth) || reachable(self.survival),
+        }
+    }
+
+    fn allows(&mut self, constraint: usize, var: usize, value: u8) -> bool {
+        self.cells[var] = value;
+        let feasible = self.feasible(constraint);
+        self.cells[var] = UNKNOWN;
+        feasible
+    }
+
+    /// Queues the constraints reading `var`.
+    fn enqueue(&mut self, var: usize) {
+        let (gen, row, col) = self.coords(var);
+        let (dr, dc) = if gen == 0 { self.displacement } else { (0, 0) };
+        let previous = self.constraint((gen + self.period - 1) % self.period, row + dr, col + dc);
+        let around = (0..9).map(|i| self.constraint(gen, row + i / 3 - 1, col + i % 3 - 1));
+        for constraint in around.chain(Some(previous)).flatten().collect::<Vec<_>>() {
+            if !self.queued[constraint] {
+                self.queued[constraint] = true;
+                self.queue.push(constraint);
+            }
+        }
+    }
+
+    fn clear_queue(&mut self) {
+        for constraint in self.queue.drain(..) {
+            self.queued[constraint] = false;
+        }
+    }
+
+    /// Decides `var` and the cells tied to it, unless one of them already
+    /// has the other value.
+    fn assign(&mut self, var: usize, value: u8) -> bool {
+        for i in 0..self.orbits[var].len() {
+            let tied = self.orbits[var][i];
+            match self.cells[tied] {
+                UNKNOWN => {
+                    self.cells[tied] = value;
+                    self.trail.push(tied);
+                    self.enqueue(tied);
+                }
+                cell if cell != value => return false,
+                _ => {}
+            }
+        }
+        true
+    }
+
+    /// Checks the queued constraints, deciding the cells they force, until
+    /// none is left or one fails.
+    fn propagate(&mut self) -> bool {
+        while let Some(constraint) = self.queue.pop() {
+            self.queued[constraint] = false;
+            if !self.feasible(constraint) {
+                return false;
+            }
+            for var in self.members(constraint).iter().flatten().copied() {
+                if self.cells[var] != UNKNOWN {
+                    continue;
+                }
+                let forced = match (
+                    self.allows(constraint, var, DEAD),
+                    self.allows(constraint, var, ALIVE),
+                ) {
+                    (true, true) => continue,
+                    (true, false) => DEAD,
+                    (false, true) => ALIVE,
+                    (false, false) => return false,
+                };
+                if !self.assign(var, forced) {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    fn undo(&mut self, mark: usize) {
+        for var in self.trail.drain(mark..) {
+            self.cells[var] = UNKNOWN;
+        }
+    }
+
+    /// Decides the cells in `order`, reporting partial patterns along the
+    /// way and complete ones when there is nothing left to decide.
+    ///
+    /// Decisions are kept on a stack rather than in recursive calls, as
+    /// there can be one per cell of every generation: each holds its
+    /// position in `order`, the next value to try and the length of the
+    /// trail before it, which is undone before trying the next value.
+    fn solve(&mut self, report: &mut Report) -> std::result::Result<(), SearchEnd> {
+        let mut decisions: Vec<(usize, usize, usize)> = Vec::new();
+        let mut from = 0;
+        loop {
+            match (from..self.order.len()).find(|&k| self.cells[self.order[k]] == UNKNOWN) {
+                Some(k) => decisions.push((k, 0, self.trail.len())),
+                None if report(self, true) => {}
+                None => return Err(SearchEnd::Stopped),
+            }
+            loop {
+                let (k, value, mark) = match decisions.last_mut() {
+                    Some(decision) => {
+                        decision.1 += 1;
+                        (decision.0, decision.1 - 1, decision.2)
+                    }
+                    None => return Ok(()),
+                };
+                self.clear_queue();
+                self.undo(mark);
+                if value == 2 {
+                    decisions.pop();
+                    continue;
+                }
+                self.nodes += 1;
+                if self.nodes > self.max_nodes {
+                    return Err(SearchEnd::GaveUp);
+                }
+                // Never true for 0, as there has been a node.
+                if self.nodes.is_multiple_of(self.report_every) && !report(self, false) {
+                    return Err(SearchEnd::Stopped);
+                }
+                if self.assign(self.order[k], [DEAD, ALIVE][value]) && self.propagate() {
+                    from = k + 1;
+                    break;
+                }
+            }
+        }
+    }
+
+    /// The alive cells of the first generation.
+    fn first_generation(&self) -> Vec<Coord> {
+        (0..(self.width * self.height) as usize)
+            .filter(|&var| self.cells[var] == ALIVE)
+            .map(|var| {
+                let (_, row, col) = self.coords(var);
+                Coord::new(row, col)
+            })
+            .collect()
+    }
+}
+
+/// Whether a pattern with `symmetry` can move by `displacement`.
+fn fits(symmetry: DrawSymmetry, (dr, dc): (i32, i32)) -> bool {
+    match symmetry {
+        DrawSymmetry::None => true,
+        DrawSymmetry::Horizontal => dc == 0,
+        DrawSymmetry::Vertical => dr == 0,
+        DrawSymmetry::Diagonal => dr == dc,
+        _ => dr == 0 && dc == 0,
+    }
+}
+
+/// The cells alive in a pattern relative to its first one, to compare
+/// patterns up to a translation.
+fn shape(cells: &[Coord]) -> Vec<Coord> {
+    let first = cells.first().copied().unwrap_or_default();
+    cells
+        .iter()
+        .map(|c| Coord::new(c.row - first.row, c.col - first.col))
+        .collect()
+}
+
+impl World {
+    /// Looks for oscillators or spaceships under the world's rule, streaming
+    /// results to `on_event` until it returns `false`.
+    pub fn search_ships(
+        &self,
+        search: &ShipSearch,
+        mut on_event: impl FnMut(SearchEvent) -> bool,
+    ) -> Result<SearchEnd> {
+        if self.automaton.is_some() {
+            return Err(Error::Search(
+                "ships are only searched under Life-like rules".to_string(),
+            ));
+        }
+        if search.period == 0 || search.width == 0 || search.height == 0 {
+            return Err(Error::Search(
+                "nothing fits in a period or box of 0".to_string(),
+            ));
+        }
+        let (width, height) = (search.width as i32, search.height as i32);
+        let square = matches!(
+            search.symmetry,
+            DrawSymmetry::Diagonal | DrawSymmetry::Rotate90
+        );
+        if square && width != height {
+            return Err(Error::Search(format!(
+                "{:?} symmetry needs a square box",
+                search.symmetry
+            )));
+        }
+        if !fits(search.symmetry, search.displacement) {
+            return Err(Error::Search(format!(
+                "{:?} symmetric patterns can't move by {:?}",
+                search.symmetry, search.displacement
+            )));
+        }
+        // Cells around the box would be born.
+        if self.rule.born(0) {
+            return Ok(SearchEnd::Exhausted);
+        }
+
+        let period = search.period as i32;
+        let size = (width * height) as usize;
+        let positions: Vec<usize> = if width <= height {
+            (0..size).collect()
+        } else {
+            (0..width)
+                .flat_map(|col| (0..height).map(move |row| (row * width + col) as usize))
+                .collect()
+        };
+        let order = positions
+            .iter()
+            .flat_map(|&cell| (0..search.period as usize).map(move |gen| gen * size + cell))
+            .collect();
+        let orbits = (0..period as usize * size)
+            .map(|var| {
+                let (gen, cell) = (var / size, (var % size) as i32);
+                let (row, col) = (cell / width, cell % width);
+                let images = search
+                    .symmetry
+                    .images((2 * row + 1 - height, 2 * col + 1 - width));
+                let images = images.into_iter().map(|(dr, dc)| {
+                    let (row, col) = ((dr + height - 1) / 2, (dc + width - 1) / 2);
+                    gen * size + (row * width + col) as usize
+                });
+                Some(var).into_iter().chain(images).collect()
+            })
+            .collect();
+        let mask = |f: &dyn Fn(u8) -> bool| (0..=8).filter(|&n| f(n)).fold(0, |m, n| m | 1 << n);
+        let constraints = (period * (width + 2) * (height + 2)) as usize;
+        let mut state = Search {
+            birth: mask(&|n| self.rule.born(n)),
+            survival: mask(&|n| self.rule.survives(n)),
+            period,
+            width,
+            height,
+            displacement: search.displacement,
+            cells: vec![UNKNOWN; period as usize * size],
+            orbits,
+            order,
+            trail: Vec::new(),
+            queue: (0..constraints).collect(),
+            queued: vec![true; constraints],
+            nodes: 0,
+            max_nodes: search.max_nodes,
+            report_every: search.report_every,
+        };
+
+        // The last generation evolves into the box grown by one: the first
+        // one can't have cells that would move past it.
+        let (dr, dc) = search.displacement;
+        let mut consistent = true;
+        for var in 0..size {
+            let (_, row, col) = state.coords(var);
+            if state.constraint(0, row + dr, col + dc).is_none() {
+                consistent &= state.assign(var, DEAD);
+            }
+        }
+        if !consistent || !state.propagate() {
+            return Ok(SearchEnd::Exhausted);
+        }
+
+        let mut seen = HashSet::new();
+        let result = state.solve(&mut |state, complete| {
+            let cells = state.first_generation();
+            if !complete {
+                return on_event(SearchEvent::Partial(self.place_found(search, &cells)));
+            }
+            if !self.has_period(search, &cells) {
+                return true;
+            }
+            let rle = self.place_found(search, &cells);
+            let name = apgcode::encode(&cells, self.rule).unwrap_or_else(|_| rle.clone());
+            !seen.insert(name) || on_event(SearchEvent::Found(rle))
+        });
+        match result {
+            Ok(()) => Ok(SearchEnd::Exhausted),
+            Err(end) => Ok(end),
+        }
+    }
+
+    /// Runs `cells` on a plane to check that they come back moved by the
+    /// displacement after the period, and not in the same shape earlier.
+    fn has_period(&self, search: &ShipSearch, cells: &[Coord]) -> bool {
+        if cells.is_empty() {
+            return false;
+        }
+        let margin = search.period as i32 + 1;
+        let mut world = World::new(
+            search.width as i32 + 2 * margin,
+            search.height as i32 + 2 * margin,
+        );
+        world.rule = self.rule;
+        world.topology = Topology::Plane;
+        for cell in cells {
+            world.put_cell((cell.row + margin, cell.col + margin), Cell::Alive);
+        }
+        let start: Vec<Coord> = world.live_cells().collect();
+        for _ in 1..search.period {
+            world.next_tick();
+            let cells: Vec<Coord> = world.live_cells().collect();
+            if cells.is_empty() || shape(&cells) == shape(&start) {
+                return false;
+            }
+        }
+        world.next_tick();
+        let (dr, dc) = search.displacement;
+        let moved = start.iter().map(|c| Coord::new(c.row + dr, c.col + dc));
+        world.live_cells().eq(moved)
+    }
+
+    /// The first generation of a search result, centered on a board the
+    /// size of this one, as RLE.
+    fn place_found(&self, search: &ShipSearch, cells: &[Coord]) -> String {
+        let width = self.width.max(search.width as i32);
+        let height = self.height.max(search.height as i32);
+        let mut board = World::new(width, height);
+        board.rule = self.rule;
+        let top = (height - search.height as i32) / 2;
+        let left = (width - search.width as i32) / 2;
+        for cell in cells {
+            board.put_cell((top + cell.row, left + cell.col), Cell::Alive);
+        }
+        board.export_rle()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The patterns found by a search on a 32×32 board.
+    fn results(rule: &str, search: &ShipSearch) -> (Vec<String>, SearchEnd) {
+        let mut world = World::new(32, 32);
+        world.set_rule(rule.parse().unwrap());
+        let mut found = Vec::new();
+        let end = world
+            .search_ships(search, |event| {
+                if let SearchEvent::Found(rle) = event {
+                    found.push(rle);
+                }
+                true
+            })
+            .unwrap();
+        (found, end)
+    }
+
+    /// Checks that `rle` comes back moved by `(dr, dc)` after `period`
+    /// generations, and not before.
+    fn assert_period(rule: &str, rle: &str, period: u32, (dr, dc): (i32, i32)) {
+        let mut world = World::new(32, 32);
+        world.set_rule(rule.parse().unwrap());
+        world.load_rle_str(rle).unwrap();
+        let start: Vec<Coord> = world.live_cells().collect();
+        for _ in 1..period {
+            world.next_tick();
+            let cells: Vec<Coord> = world.live_cells().collect();
+            assert_ne!(shape(&cells), shape(&start), "{}", rle);
+        }
+        world.next_tick();
+        let moved: Vec<Coord> = start
+            .iter()
+            .map(|c| Coord::new(c.row + dr, c.col + dc))
+            .collect();
+        assert_eq!(world.live_cells().collect::<Vec<_>>(), moved, "{}", rle);
+    }
+
+    #[test]
+    fn oscillators_test() {
+        let (found, end) = results("B3/S23", &ShipSearch::new(2, (0, 0), 3, 3));
+        assert_eq!(end, SearchEnd::Exhausted);
+        // The blinker, once whatever its phase and position.
+        assert_eq!(found.len(), 1);
+        assert_period("B3/S23", &found[0], 2, (0, 0));
+
+        let (found, _) = results("B3/S23", &ShipSearch::new(1, (0, 0), 2, 2));
+        assert_eq!(found.len(), 1);
+        assert!(found[0].ends_with("2o$2o!\n"), "{}", found[0]);
+    }
+
+    #[test]
+    fn glider_test() {
+        let (found, end) = results("B3/S23", &ShipSearch::new(4, (1, 1), 4, 4));
+        assert_eq!(end, SearchEnd::Exhausted);
+        assert_eq!(found.len(), 1);
+        assert_period("B3/S23", &found[0], 4, (1, 1));
+
+        // Nor in a 3×3 box, which only holds the glider before it moves.
+        let (found, _) = results("B3/S23", &ShipSearch::new(4, (1, 1), 3, 3));
+        assert!(found.is_empty());
+    }
+
+    #[test]
+    fn symmetry_test() {
+        // The toad, the beacon and the clock.
+        let mut search = ShipSearch::new(2, (0, 0), 4, 4);
+        search.symmetry = DrawSymmetry::Rotate180;
+        let (found, _) = results("B3/S23", &search);
+        assert_eq!(found.len(), 3);
+        for rle in &found {
+            assert_period("B3/S23", rle, 2, (0, 0));
+        }
+
+        let mut search = ShipSearch::new(4, (0, 2), 7, 4);
+        search.symmetry = DrawSymmetry::Horizontal;
+        let world = World::new(32, 32);
+        assert!(world.search_ships(&search, |_| true).is_err());
+        search.symmetry = DrawSymmetry::Diagonal;
+        assert!(world.search_ships(&search, |_| true).is_err());
+    }
+
+    #[test]
+    fn deep_box_test() {
+        // Every cell of every generation is decided before the first
+        // complete pattern, the empty one: thousands of decisions at once,
+        // made on a thread with a small stack.
+        let search = std::thread::Builder::new()
+            .stack_size(128 * 1024)
+            .spawn(|| {
+                let mut search = ShipSearch::new(2, (0, 0), 40, 40);
+                search.max_nodes = 10_000;
+                World::new(64, 64).search_ships(&search, |_| true)
+            })
+            .unwrap();
+        assert_eq!(search.join().unwrap(), Ok(SearchEnd::GaveUp));
+    }
+
+    #[test]
+    fn streaming_test() {
+        let world = World::new(32, 32);
+        let mut search = ShipSearch::new(4, (1, 1), 4, 4);
+        search.report_every = 10;
+        let mut partials = 0;
+        let end = world
+            .search_ships(&search, |event| match event {
+                SearchEvent::Partial(_) => {
+                    partials += 1;
+                    true
+                }
+                SearchEvent::Found(_) => false,
+            })
+            .unwrap();
+        assert_eq!(end, SearchEnd::Stopped);
+        assert!(partials > 0);
+
+        search.max_nodes = 5;
+        assert_eq!(world.search_ships(&search, |_| true), Ok(SearchEnd::GaveUp));
+
+        let mut world = World::new(8, 8);
+        world.set_automaton(Some(crate::Automaton::WireWorld));
+        assert!(world.search_ships(&search, |_| true).is_err());
+    }
+}
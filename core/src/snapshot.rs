@@ -0,0 +1,587 @@
+//! Binary snapshots of a `World`.
+//!
+//! A snapshot keeps everything needed to resume a simulation: dimensions,
+//! generation count, rule or automaton, topology, ants and cells. All
+//! integers are little endian:
+//!
+//! | bytes  | content                                           |
+//! |--------|---------------------------------------------------|
+//! | 4      | magic, `GOLS`                                     |
+//! | 1      | format version                                    |
+//! | 1      | topology: 0 for torus, 1 for plane                |
+//! | 4      | width                                             |
+//! | 4      | height                                            |
+//! | 4      | generations                                       |
+//! | 1 + n  | rule length and rule, in `B3/S23` notation        |
+//! | 1      | automaton: 0 for none, 1 rule table, 2 by name    |
+//! | 4 + n  | text length and text, for rule tables             |
+//! | 1 + n  | name length and name, for automata saved by name  |
+//! | 4      | number of ants                                    |
+//! | 14 + n | each ant: row, column, direction, state, turmite  |
+//! | rest   | cells, row by row, PackBits coded                 |
+//!
+//! WireWorld and block rules are saved by the name they parse from, rule
+//! tables by their text. Block rules need nothing more: the position of
+//! their partition follows from the generation count. Ants face north,
+//! east, south or west for 0 to 3, and their turmite is written in Golly's
+//! notation after its length.
+//!
+//! Cells take one byte each, their state, under automata with more than two
+//! states, and one bit each otherwise. Bits are packed least significant
+//! first, so the first byte holds the first eight cells of the top row. The
+//! packed bytes are then run length encoded with PackBits, which shrinks the
+//! long blank stretches of most boards to a couple of bytes each.
+//!
+//! Version 1 snapshots, which stop at the rule and the cells, and version 2
+//! ones, which have no ants, still load.
+
+use super::turmite::{Direction, Turmite};
+use super::{Automaton, Cell, Coord, Error, Result, Topology, World};
+
+const MAGIC: &[u8; 4] = b"GOLS";
+
+/// Current snapshot format version.
+pub const VERSION: u8 = 3;
+
+/// Longest run or literal sequence a PackBits header can describe.
+const MAX_RUN: usize = 128;
+
+const DIRECTIONS: [Direction; 4] = [
+    Direction::North,
+    Direction::East,
+    Direction::South,
+    Direction::West,
+];
+
+impl World {
+    /// Serializes the whole world state; see `load_snapshot`.
+    pub fn save_snapshot(&self) -> Vec<u8> {
+        let rule = self.rule.to_string();
+        let mut bytes = Vec::with_capacity(24 + rule.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.push(match self.topology {
+            Topology::Torus => 0,
+            Topology::Plane => 1,
+        });
+        put_u32(&mut bytes, self.width as u32);
+        put_u32(&mut bytes, self.height as u32);
+        put_u32(&mut bytes, self.generations);
+        bytes.push(rule.len() as u8);
+        bytes.extend_from_slice(rule.as_bytes());
+
+        match self.automaton {
+            Some(Automaton::Table(ref table)) => {
+                bytes.push(1);
+                put_text(&mut bytes, table.source());
+            }
+            Some(ref automaton) => {
+                bytes.push(2);
+                bytes.push(automaton.name().len() as u8);
+                bytes.extend_from_slice(automaton.name().as_bytes());
+            }
+            None => bytes.push(0),
+        }
+        put_u32(&mut bytes, self.ants.len() as u32);
+        for ant in &self.ants {
+            put_u32(&mut bytes, ant.position().row as u32);
+            put_u32(&mut bytes, ant.position().col as u32);
+            bytes.push(ant.direction() as u8);
+            bytes.push(ant.state());
+            put_text(&mut bytes, &ant.turmite().to_string());
+        }
+
+        if self.state_count() > 2 {
+            pack_bits(&self.states, &mut bytes);
+        } else {
+            pack_bits(&bit_pack(&self.cells), &mut bytes);
+        }
+        bytes
+    }
+
+    /// Rebuilds a world saved with `save_snapshot`. Snapshots from an
+    /// unknown format version, or whose cells don't match their dimensions
+    /// or automaton, are rejected.
+    pub fn load_snapshot(bytes: &[u8]) -> Result<World> {
+        let mut reader = Reader { bytes, pos: 0 };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(invalid("not a snapshot"));
+        }
+        let version = reader.u8()?;
+        if version == 0 || version > VERSION {
+            return Err(Error::InvalidSnapshot(format!(
+                "unsupported version {} (expected {})",
+                version, VERSION
+            )));
+        }
+        let topology = match reader.u8()? {
+            0 => Topology::Torus,
+            1 => Topology::Plane,
+            n => return Err(Error::InvalidSnapshot(format!("unknown topology {}", n))),
+        };
+        let width = reader.u32()?;
+        let height = reader.u32()?;
+        let generations = reader.u32()?;
+        let rule_len = reader.u8()? as usize;
+        let rule = reader.text(rule_len)?.parse()?;
+        let automaton = if version == 1 {
+            None
+        } else {
+            reader.automaton()?
+        };
+        let ants = if version < 3 {
+            Vec::new()
+        } else {
+            reader.ants()?
+        };
+
+        let size = (width as u64) * (height as u64);
+        if width == 0 || height == 0 || width > i32::MAX as u32 || size > i32::MAX as u64 {
+            return Err(Error::InvalidSnapshot(format!(
+                "invalid size {}x{}",
+                width, height
+            )));
+        }
+        let size = size as usize;
+        let state_count = automaton.as_ref().map_or(2, Automaton::states);
+        let states: Vec<u8> = if state_count > 2 {
+            unpack_bits(reader.rest(), size)?
+        } else {
+            let packed = unpack_bits(reader.rest(), size.div_ceil(8))?;
+            bit_unpack(&packed, size)
+                .into_iter()
+                .map(u8::from)
+                .collect()
+        };
+        if let Some(state) = states.iter().find(|&&state| state as usize >= state_count) {
+            return Err(Error::InvalidSnapshot(format!(
+                "state {} out of {} states",
+                state, state_count
+            )));
+        }
+
+        let mut world = World::new(width as i32, height as i32);
+        world.rule = rule;
+        world.topology = topology;
+        world.set_automaton(automaton);
+        world.cells = states
+            .iter()
+            .map(|&state| if state == 0 { Cell::Dead } else { Cell::Alive })
+            .collect();
+        world.cache = world.cells.clone();
+        if world.automaton.is_some() {
+            world.states = states;
+            world.state_cache = world.states.clone();
+        }
+        for (turmite, at, direction, state) in ants {
+            if at.row < 0 || at.row >= world.height || at.col < 0 || at.col >= world.width {
+                return Err(invalid("ant out of the board"));
+            }
+            world.restore_ant(turmite, at, direction, state)?;
+        }
+        world.generations = generations;
+        world.mark_all_changed();
+        Ok(world)
+    }
+}
+
+fn put_u32(bytes: &mut Vec<u8>, n: u32) {
+    bytes.extend_from_slice(&n.to_le_bytes());
+}
+
+/// Text too long for a one byte length, after its length on 4 bytes.
+fn put_text(bytes: &mut Vec<u8>, text: &str) {
+    put_u32(bytes, text.len() as u32);
+    bytes.extend_from_slice(text.as_bytes());
+}
+
+fn invalid(reason: &str) -> Error {
+    Error::InvalidSnapshot(reason.to_string())
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        if self.bytes.len() - self.pos < n {
+            return Err(invalid("unexpected end of data"));
+        }
+        let bytes = &self.bytes[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let mut buf = [0; 4];
+        buf.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    fn text(&mut self, len: usize) -> Result<&'a str> {
+        std::str::from_utf8(self.take(len)?).map_err(|_| invalid("text is not valid UTF-8"))
+    }
+
+    fn automaton(&mut self) -> Result<Option<Automaton>> {
+        match self.u8()? {
+            0 => Ok(None),
+            1 => {
+                let len = self.u32()? as usize;
+                Ok(Some(Automaton::Table(self.text(len)?.parse()?)))
+            }
+            2 => {
+                let len = self.u8()? as usize;
+                Ok(Some(self.text(len)?.parse()?))
+            }
+            n => Err(Error::InvalidSnapshot(format!("unknown automaton {}", n))),
+        }
+    }
+
+    fn ants(&mut self) -> Result<Vec<(Turmite, Coord, Direction, u8)>> {
+        let count = self.u32()?;
+        let mut ants = Vec::new();
+        for _ in 0..count {
+            let at = Coord::new(self.u32()? as i32, self.u32()? as i32);
+            let direction = *DIRECTIONS
+                .get(self.u8()? as usize)
+                .ok_or_else(|| invalid("unknown direction"))?;
+            let state = self.u8()?;
+            let len = self.u32()? as usize;
+            ants.push((self.text(len)?.parse()?, at, direction, state));
+        }
+        Ok(ants)
+    }
+
+    fn rest(&mut self) -> &'a [u8] {
+        let rest = &self.bytes[self.pos..];
+        self.pos = self.bytes.len();
+        rest
+    }
+}
+
+fn bit_pack(cells: &[Cell]) -> Vec<u8> {
+    cells
+        .chunks(8)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .enumerate()
+                .fold(0, |byte, (bit, cell)| byte | (*cell as u8) << bit)
+        })
+        .collect()
+}
+
+fn bit_unpack(packed: &[u8], size: usize) -> Vec<Cell> {
+    (0..size)
+        .map(|idx| match packed[idx / 8] >> (idx % 8) & 1 {
+            0 => Cell::Dead,
+            _ => Cell::Alive,
+        })
+        .collect()
+}
+
+/// PackBits: a header `n` below 128 is followed by `n + 1` literal bytes,
+/// a header `n` above 128 by a single byte repeated `257 - n` times.
+fn pack_bits(data: &[u8], out: &mut Vec<u8>) {
+    let mut literals = 0..0;
+    let mut i = 0;
+    while i < data.len() {
+        let run = data[i..]
+            .iter()
+            .take(MAX_RUN)
+            .take_while(|b| **b == data[i])
+            .count();
+        if run >= 3 {
+            flush_literals(data, literals, out);
+            out.push((257 - run) as u8);
+            out.push(data[i]);
+            i += run;
+            literals = i..i;
+        } else {
+            i += run;
+            literals.end = i;
+            if literals.len() >= MAX_RUN {
+                flush_literals(data, literals.start..literals.start + MAX_RUN, out);
+                literals.start += MAX_RUN;
+            }
+        }
+    }
+    flush_literals(data, literals, out);
+}
+
+fn flush_literals(data: &[u8], literals: std::ops::Range<usize>, out: &mut Vec<u8>) {
+    if !literals.is_empty() {
+        out.push((literals.len() - 1) as u8);
+        out.extend_from_slice(&data[literals]);
+    }
+}
+
+fn unpack_bits(data: &[u8], expected: usize) -> Result<Vec<u8>> {
+    // Two bytes unpack to at most a run of `MAX_RUN`, so bigger sizes can't
+    // be right and aren't worth allocating for.
+    let most = data.len().div_ceil(2) * MAX_RUN;
+    if expected > most {
+        return Err(Error::InvalidSnapshot(format!(
+            "expected {} bytes of cells, found at most {}",
+            expected, most
+        )));
+    }
+    let mut out = Vec::with_capacity(expected);
+    let mut reader = Reader {
+        bytes: data,
+        pos: 0,
+    };
+    while reader.pos < data.len() {
+        match reader.u8()? {
+            n @ 0..=127 => out.extend_from_slice(reader.take(n as usize + 1)?),
+            128 => {}
+            n => {
+                let byte = reader.u8()?;
+                out.extend(std::iter::repeat_n(byte, 257 - n as usize));
+            }
+        }
+        if out.len() > expected {
+            break;
+        }
+    }
+    if out.len() != expected {
+        return Err(Error::InvalidSnapshot(format!(
+            "expected {} bytes of cells, found {}",
+            expected,
+            out.len()
+        )));
+    }
+    Ok(out)
+}
+
+#[cfg(feature = "serde")]
+mod serde_support {
+    use serde::de::{Deserialize, Deserializer, Error as _};
+    use serde::ser::{Serialize, Serializer};
+
+    use super::super::{Rule, World};
+
+    /// Worlds are serialized as their binary snapshot.
+    impl Serialize for World {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_bytes(&self.save_snapshot())
+        }
+    }
+
+    impl<'de> Deserialize<'de> for World {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let bytes = Vec::<u8>::deserialize(deserializer)?;
+            World::load_snapshot(&bytes).map_err(D::Error::custom)
+        }
+    }
+
+    /// Rules are serialized in `B3/S23` notation.
+    impl Serialize for Rule {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_str(self)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Rule {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            String::deserialize(deserializer)?
+                .parse()
+                .map_err(D::Error::custom)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glider_world() -> World {
+        let mut world = World::new(37, 21);
+        world.set_rule("B36/S23".parse().unwrap());
+        world.set_topology(Topology::Plane);
+        world.load_rle_str("x = 3, y = 3\nbo$2bo$3o!").unwrap();
+        world.next_tick();
+        world.next_tick();
+        world
+    }
+
+    #[test]
+    fn snapshot_round_trip_test() {
+        let world = glider_world();
+        let bytes = world.save_snapshot();
+        let mut loaded = World::load_snapshot(&bytes).unwrap();
+
+        assert_eq!(loaded.width(), 37);
+        assert_eq!(loaded.height(), 21);
+        assert_eq!(loaded.generations(), 2);
+        assert_eq!(loaded.rule(), world.rule());
+        assert_eq!(loaded.topology(), Topology::Plane);
+        assert_eq!(loaded.cells(), world.cells());
+
+        // The restored world keeps evolving like the original.
+        let mut world = world;
+        world.next_tick();
+        loaded.next_tick();
+        assert_eq!(loaded.cells(), world.cells());
+    }
+
+    #[test]
+    fn snapshot_is_compact_test() {
+        let world = glider_world();
+        // 777 cells fit in 98 packed bytes, mostly blank.
+        assert!(world.save_snapshot().len() < 50);
+    }
+
+    #[test]
+    fn snapshot_validation_test() {
+        let bytes = glider_world().save_snapshot();
+
+        assert!(World::load_snapshot(b"GOL").is_err());
+        assert!(World::load_snapshot(b"RLE!\x01").is_err());
+
+        let mut other_version = bytes.clone();
+        other_version[4] = VERSION + 1;
+        match World::load_snapshot(&other_version) {
+            Err(Error::InvalidSnapshot(e)) => assert!(e.contains("version")),
+            _ => panic!("expected a version error"),
+        }
+
+        // Claiming a wider board than the cells provided.
+        let mut wider = bytes.clone();
+        wider[6] += 1;
+        assert!(World::load_snapshot(&wider).is_err());
+
+        // A huge board with next to no cells is rejected before allocating
+        // room for them.
+        let mut huge = bytes.clone();
+        huge[6..14].copy_from_slice(&[0x40, 0x9c, 0, 0, 0x40, 0x9c, 0, 0]);
+        match World::load_snapshot(&huge) {
+            Err(Error::InvalidSnapshot(e)) => assert!(e.contains("at most"), "{}", e),
+            _ => panic!("expected a size error"),
+        }
+
+        let truncated = &bytes[..bytes.len() - 2];
+        assert!(World::load_snapshot(truncated).is_err());
+
+        let mut trailing = bytes.clone();
+        trailing.extend_from_slice(&[0, 0]);
+        assert!(World::load_snapshot(&trailing).is_err());
+    }
+
+    /// Checks that `world` loads back from its snapshot and keeps running the
+    /// same way.
+    fn assert_round_trip(mut world: World) {
+        let mut loaded = World::load_snapshot(&world.save_snapshot()).unwrap();
+        assert_eq!(loaded.generations(), world.generations());
+        assert_eq!(loaded.automaton(), world.automaton());
+        assert_eq!(loaded.ants(), world.ants());
+        for _ in 0..8 {
+            assert!(loaded.states().eq(world.states()));
+            world.next_tick();
+            loaded.next_tick();
+        }
+        assert!(loaded.states().eq(world.states()));
+        assert_eq!(loaded.ants(), world.ants());
+    }
+
+    #[test]
+    fn automata_round_trip_test() {
+        let mut clock = World::new(12, 9);
+        clock.set_automaton(Some(Automaton::WireWorld));
+        clock.load_rle_str("x = 6, y = 3\n.BA2C$C4.C$.4C!").unwrap();
+        clock.next_tick();
+        assert_round_trip(clock);
+
+        // Cells grow south and fade through state 2.
+        let mut table = World::new(10, 10);
+        table
+            .load_rule_table(
+                "@RULE fade\n@TABLE\nn_states:3\nneighborhood:vonNeumann\n0,1,0,0,0,1\n1,0,0,0,0,2\n2,0,0,0,0,0\n",
+            )
+            .unwrap();
+        table.set_state((2, 3), 1);
+        table.set_state((5, 6), 2);
+        assert_round_trip(table);
+
+        // Block rules continue with the partition of an odd generation.
+        for rule in &["BBM", "MS,D15;14;13;3;11;5;6;1;7;9;10;2;12;4;8;0"] {
+            let mut world = World::new(8, 8);
+            world.set_automaton(Some(rule.parse().unwrap()));
+            world.load_rle_str("x = 3, y = 2\n2o$bo!").unwrap();
+            world.next_tick();
+            assert_eq!(world.generations() % 2, 1);
+            assert_round_trip(world);
+        }
+    }
+
+    #[test]
+    fn ants_round_trip_test() {
+        let mut ants = World::new(16, 16);
+        ants.set_rule("B/S012345678".parse().unwrap());
+        ants.add_ant(Turmite::langtons_ant(), (5, 5), Direction::North)
+            .unwrap();
+        let turmite = "{{{1,2,1},{0,8,0}},{{1,1,0},{1,1,1}}}".parse().unwrap();
+        ants.add_ant(turmite, (10, 2), Direction::East).unwrap();
+        for _ in 0..7 {
+            ants.next_tick();
+        }
+        assert_eq!(ants.ants()[1].state(), 1);
+        assert_round_trip(ants);
+    }
+
+    #[test]
+    fn old_versions_test() {
+        // Version 1 had nothing between the rule and the cells.
+        let world = glider_world();
+        let bytes = world.save_snapshot();
+        let rule_end = 19 + world.rule().to_string().len();
+        let mut old = bytes[..rule_end].to_vec();
+        old[4] = 1;
+        old.extend_from_slice(&bytes[rule_end + 5..]);
+
+        let loaded = World::load_snapshot(&old).unwrap();
+        assert_eq!(loaded.rule(), world.rule());
+        assert_eq!(loaded.cells(), world.cells());
+
+        // Version 2 had no ants after the automaton.
+        let mut old = bytes[..rule_end + 1].to_vec();
+        old[4] = 2;
+        old.extend_from_slice(&bytes[rule_end + 5..]);
+        let loaded = World::load_snapshot(&old).unwrap();
+        assert_eq!(loaded.cells(), world.cells());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_test() {
+        let world = glider_world();
+        let json = serde_json::to_string(&world).unwrap();
+        let loaded: World = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.cells(), world.cells());
+
+        let rule = serde_json::to_string(&world.rule()).unwrap();
+        assert_eq!(rule, "\"B36/S23\"");
+        assert_eq!(
+            serde_json::from_str::<Topology>("\"Plane\"").unwrap(),
+            Topology::Plane
+        );
+    }
+
+    #[test]
+    fn pack_bits_test() {
+        let mut data = vec![0; 300];
+        data.extend((0..200).map(|n| n as u8));
+        data.extend_from_slice(&[7, 7, 1, 7, 7, 7]);
+
+        let mut packed = Vec::new();
+        pack_bits(&data, &mut packed);
+        assert!(packed.len() < data.len());
+        assert_eq!(unpack_bits(&packed, data.len()).unwrap(), data);
+    }
+}
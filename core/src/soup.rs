@@ -0,0 +1,316 @@
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg32;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use super::{Cell, Error, Rect, World};
+
+/// Soup symmetries, named after the ones used by apgsearch.
+///
+/// The digit suffix tells where the symmetry center lies relative to the
+/// bottom-right cell of the soup rectangle: `1` on its center, `2` on the
+/// middle of its bottom edge and `4` on its bottom-right corner.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Symmetry {
+    C1,
+    C2_1,
+    C2_2,
+    C2_4,
+    C4_1,
+    C4_4,
+    D2Plus1,
+    D2Plus2,
+    D2X,
+    D4Plus1,
+    D4Plus2,
+    D4Plus4,
+    D4X1,
+    D4X4,
+    D8_1,
+    D8_4,
+}
+
+impl FromStr for Symmetry {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "C1" => Symmetry::C1,
+            "C2_1" => Symmetry::C2_1,
+            "C2_2" => Symmetry::C2_2,
+            "C2_4" => Symmetry::C2_4,
+            "C4_1" => Symmetry::C4_1,
+            "C4_4" => Symmetry::C4_4,
+            "D2_+1" => Symmetry::D2Plus1,
+            "D2_+2" => Symmetry::D2Plus2,
+            "D2_x" => Symmetry::D2X,
+            "D4_+1" => Symmetry::D4Plus1,
+            "D4_+2" => Symmetry::D4Plus2,
+            "D4_+4" => Symmetry::D4Plus4,
+            "D4_x1" => Symmetry::D4X1,
+            "D4_x4" => Symmetry::D4X4,
+            "D8_1" => Symmetry::D8_1,
+            "D8_4" => Symmetry::D8_4,
+            _ => return Err(Error::UnknownSymmetry(s.to_string())),
+        })
+    }
+}
+
+impl fmt::Display for Symmetry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Symmetry::C1 => "C1",
+            Symmetry::C2_1 => "C2_1",
+            Symmetry::C2_2 => "C2_2",
+            Symmetry::C2_4 => "C2_4",
+            Symmetry::C4_1 => "C4_1",
+            Symmetry::C4_4 => "C4_4",
+            Symmetry::D2Plus1 => "D2_+1",
+            Symmetry::D2Plus2 => "D2_+2",
+            Symmetry::D2X => "D2_x",
+            Symmetry::D4Plus1 => "D4_+1",
+            Symmetry::D4Plus2 => "D4_+2",
+            Symmetry::D4Plus4 => "D4_+4",
+            Symmetry::D4X1 => "D4_x1",
+            Symmetry::D4X4 => "D4_x4",
+            Symmetry::D8_1 => "D8_1",
+            Symmetry::D8_4 => "D8_4",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Isometries of the grid, applied to coordinates relative to the
+/// symmetry center.
+#[derive(Clone, Copy)]
+enum Transform {
+    Identity,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    FlipRows,
+    FlipCols,
+    Transpose,
+    AntiTranspose,
+}
+
+impl Transform {
+    fn apply(self, (dy, dx): (i32, i32)) -> (i32, i32) {
+        match self {
+            Transform::Identity => (dy, dx),
+            Transform::Rotate90 => (dx, -dy),
+            Transform::Rotate180 => (-dy, -dx),
+            Transform::Rotate270 => (-dx, dy),
+            Transform::FlipRows => (-dy, dx),
+            Transform::FlipCols => (dy, -dx),
+            Transform::Transpose => (dx, dy),
+            Transform::AntiTranspose => (-dx, -dy),
+        }
+    }
+}
+
+enum Center {
+    Cell,
+    Edge,
+    Corner,
+}
+
+impl Symmetry {
+    fn group(self) -> &'static [Transform] {
+        use self::Transform::*;
+        match self {
+            Symmetry::C1 => &[Identity],
+            Symmetry::C2_1 | Symmetry::C2_2 | Symmetry::C2_4 => &[Identity, Rotate180],
+            Symmetry::C4_1 | Symmetry::C4_4 => &[Identity, Rotate90, Rotate180, Rotate270],
+            Symmetry::D2Plus1 | Symmetry::D2Plus2 => &[Identity, FlipRows],
+            Symmetry::D2X => &[Identity, Transpose],
+            Symmetry::D4Plus1 | Symmetry::D4Plus2 | Symmetry::D4Plus4 => {
+                &[Identity, FlipRows, FlipCols, Rotate180]
+            }
+            Symmetry::D4X1 | Symmetry::D4X4 => &[Identity, Transpose, AntiTranspose, Rotate180],
+            Symmetry::D8_1 | Symmetry::D8_4 => &[
+                Identity,
+                Rotate90,
+                Rotate180,
+                Rotate270,
+                FlipRows,
+                FlipCols,
+                Transpose,
+                AntiTranspose,
+            ],
+        }
+    }
+
+    fn center(self) -> Center {
+        match self {
+            Symmetry::C1
+            | Symmetry::C2_1
+            | Symmetry::C4_1
+            | Symmetry::D2Plus1
+            | Symmetry::D4Plus1
+            | Symmetry::D4X1
+            | Symmetry::D8_1 => Center::Cell,
+            Symmetry::C2_2 | Symmetry::D4Plus2 => Center::Edge,
+            Symmetry::C2_4
+            | Symmetry::C4_4
+            | Symmetry::D2Plus2
+            | Symmetry::D2X
+            | Symmetry::D4Plus4
+            | Symmetry::D4X4
+            | Symmetry::D8_4 => Center::Corner,
+        }
+    }
+
+    /// Images of `(row, col)` under the symmetry group, for a soup whose
+    /// base rectangle is `rect`.
+    ///
+    /// Coordinates are doubled during the computation so that centers lying
+    /// on cell edges and corners stay integral: cell `(r, c)` covers the point
+    /// `(2r + 1, 2c + 1)`.
+    fn orbit(self, rect: &Rect<i32>, (row, col): (i32, i32)) -> Vec<(i32, i32)> {
+        let bottom = 2 * (rect.y + rect.height);
+        let right = 2 * (rect.x + rect.width);
+        let (cy, cx) = match self.center() {
+            Center::Cell => (bottom - 1, right - 1),
+            Center::Edge => (bottom, right - 1),
+            Center::Corner => (bottom, right),
+        };
+        let relative = (2 * row + 1 - cy, 2 * col + 1 - cx);
+        self.group()
+            .iter()
+            .map(|t| {
+                let (dy, dx) = t.apply(relative);
+                ((cy + dy - 1).div_euclid(2), (cx + dx - 1).div_euclid(2))
+            })
+            .collect()
+    }
+}
+
+fn rng(seed: u32) -> Pcg32 {
+    Pcg32::seed_from_u64(u64::from(seed))
+}
+
+fn random_cell<R: Rng>(rng: &mut R, density: f64) -> Cell {
+    if rng.gen::<f64>() < density {
+        Cell::Alive
+    } else {
+        Cell::Dead
+    }
+}
+
+impl World {
+    /// Replaces the whole board with random cells, each one being alive with
+    /// probability `density`. The same seed always yields the same board.
+    pub fn randomize(&mut self, density: f64, seed: u32) {
+        let mut rng = rng(seed);
+        for row in 0..self.height {
+            for col in 0..self.width {
+                let cell = random_cell(&mut rng, density);
+                self.put_cell((row, col), cell);
+            }
+        }
+    }
+
+    /// Draws a random soup in `rect` and mirrors it according to `symmetry`.
+    /// Depending on the symmetry, the soup extends below and to the right of
+    /// `rect`.
+    ///
+    /// Each orbit of the symmetry group gets a single random draw, made when
+    /// its first cell is met in row-major order over `rect`.
+    pub fn random_soup(&mut self, rect: &Rect<i32>, density: f64, symmetry: Symmetry, seed: u32) {
+        let mut rng = rng(seed);
+        let mut drawn = HashMap::new();
+        for row in rect.y..rect.y + rect.height {
+            for col in rect.x..rect.x + rect.width {
+                let orbit = symmetry.orbit(rect, (row, col));
+                let representative = *orbit.iter().min().unwrap();
+                let cell = *drawn
+                    .entry(representative)
+                    .or_insert_with(|| random_cell(&mut rng, density));
+                for at in orbit {
+                    self.put_cell(at, cell);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Image = fn((i32, i32)) -> (i32, i32);
+
+    fn alive_cells(world: &World) -> Vec<(i32, i32)> {
+        world.live_cells().map(|c| (c.row, c.col)).collect()
+    }
+
+    #[test]
+    fn randomize_is_reproducible_test() {
+        let mut a = World::new(32, 32);
+        let mut b = World::new(32, 32);
+        a.randomize(0.5, 42);
+        b.randomize(0.5, 42);
+        assert_eq!(alive_cells(&a), alive_cells(&b));
+
+        b.randomize(0.5, 43);
+        assert_ne!(alive_cells(&a), alive_cells(&b));
+    }
+
+    #[test]
+    fn randomize_density_test() {
+        let mut world = World::new(16, 16);
+        world.randomize(0.0, 1);
+        assert!(alive_cells(&world).is_empty());
+        world.randomize(1.0, 1);
+        assert_eq!(alive_cells(&world).len(), 256);
+    }
+
+    #[test]
+    fn symmetry_names_test() {
+        for name in &[
+            "C1", "C2_1", "C2_2", "C2_4", "C4_1", "C4_4", "D2_+1", "D2_+2", "D2_x", "D4_+1",
+            "D4_+2", "D4_+4", "D4_x1", "D4_x4", "D8_1", "D8_4",
+        ] {
+            assert_eq!(name.parse::<Symmetry>().unwrap().to_string(), *name);
+        }
+        assert!("C3".parse::<Symmetry>().is_err());
+    }
+
+    #[test]
+    fn symmetric_soups_test() {
+        let rect = Rect {
+            x: 4,
+            y: 4,
+            width: 8,
+            height: 8,
+        };
+        let checks: Vec<(Symmetry, Image)> = vec![
+            // 180 degrees around the corner (12, 12)
+            (Symmetry::C2_4, |(r, c)| (23 - r, 23 - c)),
+            // 180 degrees around the center of cell (11, 11)
+            (Symmetry::C2_1, |(r, c)| (22 - r, 22 - c)),
+            // 180 degrees around the middle of the bottom edge of (11, 11)
+            (Symmetry::C2_2, |(r, c)| (23 - r, 22 - c)),
+            (Symmetry::C4_4, |(r, c)| (c, 23 - r)),
+            (Symmetry::D2Plus2, |(r, c)| (23 - r, c)),
+            (Symmetry::D2X, |(r, c)| (c, r)),
+            (Symmetry::D8_4, |(r, c)| (c, r)),
+            (Symmetry::D8_4, |(r, c)| (r, 23 - c)),
+        ];
+
+        for (symmetry, image) in checks {
+            let mut world = World::new(32, 32);
+            world.random_soup(&rect, 0.5, symmetry, 7);
+            let cells = alive_cells(&world);
+            assert!(!cells.is_empty());
+            for cell in cells.iter() {
+                let (r, c) = image(*cell);
+                assert_eq!(world.cell((r, c)), Cell::Alive, "{:?} {:?}", symmetry, cell);
+            }
+        }
+    }
+}
@@ -0,0 +1,210 @@
+//! Detecting when a board settles into a cycle.
+//!
+//! Boards are told apart by a hash of their cell states, updated from
+//! `changed_cells` at every generation so that a step costs no more than
+//! `next_tick`. A board has settled when its hash comes back.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+
+use super::{apgcode, Automaton, Cell, Coord, Topology, World};
+
+/// Objects changing this close to the edges of a plane are taken off it.
+const EDGE: i32 = 4;
+
+/// How a board settled, see `World::run_until_stable`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Stable {
+    /// First generation of the cycle the board settled into.
+    pub generation: u32,
+    pub period: u32,
+    /// The cells of the objects taken off the board as they reached the
+    /// edges of a plane, as they were then.
+    pub escaped: Vec<Vec<Coord>>,
+}
+
+/// A random looking hash of `state` at `idx`, from SplitMix64.
+fn cell_hash(idx: usize, state: u8) -> u64 {
+    let mut z = ((idx as u64) << 8 | u64::from(state)).wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+impl World {
+    /// Runs at most `max_generations` generations, until the board comes
+    /// back to one it had in the last `apgcode::MAX_PERIOD` generations.
+    /// The board is left at the end of the first period of the cycle.
+    ///
+    /// On a plane under a Life-like rule, objects changing near the edges
+    /// are taken off the board instead of crashing there, as if the plane
+    /// went on: gliders flying away don't keep it from settling.
+    pub fn run_until_stable(&mut self, max_generations: u32) -> Option<Stable> {
+        let mut states: Vec<u8> = self.states().collect();
+        let mut hash = states
+            .iter()
+            .enumerate()
+            .fold(0, |hash, (idx, &state)| hash ^ cell_hash(idx, state));
+        let mut escaped = Vec::new();
+        let key = hash ^ self.extra_hash();
+        let mut seen = HashMap::new();
+        let mut recent = VecDeque::new();
+        seen.insert(key, self.generations);
+        recent.push_back((key, self.generations));
+
+        for _ in 0..max_generations {
+            self.next_tick();
+            escaped.extend(self.remove_escaping());
+            for &idx in &self.changed_cells {
+                let idx = idx as usize;
+                let state = self.state_at(idx);
+                let previous = std::mem::replace(&mut states[idx], state);
+                hash ^= cell_hash(idx, previous) ^ cell_hash(idx, state);
+            }
+
+            let key = hash ^ self.extra_hash();
+            if let Some(&generation) = seen.get(&key) {
+                return Some(Stable {
+                    generation,
+                    period: self.generations - generation,
+                    escaped,
+                });
+            }
+            seen.insert(key, self.generations);
+            recent.push_back((key, self.generations));
+            if recent.len() > apgcode::MAX_PERIOD as usize {
+                let (key, generation) = recent.pop_front().unwrap();
+                if seen.get(&key) == Some(&generation) {
+                    seen.remove(&key);
+                }
+            }
+        }
+        None
+    }
+
+    /// What matters besides the cells: the ants, and the partition of block
+    /// rules.
+    fn extra_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        if let Some(Automaton::Block(_)) = self.automaton {
+            (self.generations % 2).hash(&mut hasher);
+        }
+        for ant in &self.ants {
+            ant.position().hash(&mut hasher);
+            (ant.direction() as u8).hash(&mut hasher);
+            ant.state().hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    /// Takes the objects that changed close to the edges of a plane at the
+    /// last generation off the board, returning their cells.
+    fn remove_escaping(&mut self) -> Vec<Vec<Coord>> {
+        if self.topology != Topology::Plane || self.automaton.is_some() {
+            return Vec::new();
+        }
+        let near_edge = |(row, col): (i32, i32)| {
+            row < EDGE || col < EDGE || row >= self.height - EDGE || col >= self.width - EDGE
+        };
+        let starts: Vec<Coord> = self
+            .changed_cells
+            .iter()
+            .map(|&idx| self.to_coords(idx))
+            .filter(|&at| near_edge(at))
+            .map(|(row, col)| Coord::new(row, col))
+            .collect();
+
+        let mut objects = Vec::new();
+        let mut checked = HashSet::new();
+        for start in starts {
+            if self.cell(start) == Cell::Dead || !checked.insert(start) {
+                continue;
+            }
+            // The cells reached from `start` by steps of at most two cells.
+            let mut object = vec![start];
+            let mut next = 0;
+            while next < object.len() {
+                let at = object[next];
+                next += 1;
+                for r in -2..=2 {
+                    for c in -2..=2 {
+                        let (row, col) = (at.row + r, at.col + c);
+                        let on_board =
+                            row >= 0 && row < self.height && col >= 0 && col < self.width;
+                        if on_board
+                            && self.cell((row, col)) == Cell::Alive
+                            && checked.insert(Coord::new(row, col))
+                        {
+                            object.push(Coord::new(row, col));
+                        }
+                    }
+                }
+            }
+            for &at in &object {
+                self.put_cell(at, Cell::Dead);
+            }
+            objects.push(object);
+        }
+        objects
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(width: i32, height: i32, topology: Topology, rle: &str) -> World {
+        let mut world = World::new(width, height);
+        world.set_topology(topology);
+        world.load_rle_str(rle).unwrap();
+        world
+    }
+
+    #[test]
+    fn periods_test() {
+        let mut block = board(16, 16, Topology::Torus, "x = 2, y = 2\n2o$2o!");
+        let stable = block.run_until_stable(10).unwrap();
+        assert_eq!((stable.generation, stable.period), (0, 1));
+
+        let mut blinker = board(16, 16, Topology::Plane, "x = 3, y = 1\n3o!");
+        blinker.next_tick();
+        let stable = blinker.run_until_stable(10).unwrap();
+        assert_eq!((stable.generation, stable.period), (1, 2));
+        assert_eq!(blinker.generations(), 3);
+
+        // A glider comes back after going once around a torus.
+        let mut glider = board(8, 8, Topology::Torus, "x = 3, y = 3\nbo$2bo$3o!");
+        let stable = glider.run_until_stable(100).unwrap();
+        assert_eq!((stable.generation, stable.period), (0, 32));
+        assert!(stable.escaped.is_empty());
+
+        let mut clock = World::new(12, 9);
+        clock.set_automaton(Some(Automaton::WireWorld));
+        clock.load_rle_str("x = 6, y = 3\n.BA2C$C4.C$.4C!").unwrap();
+        let stable = clock.run_until_stable(100).unwrap();
+        assert_eq!((stable.generation, stable.period), (0, 10));
+    }
+
+    #[test]
+    fn escaping_test() {
+        // The R-pentomino settles at generation 1103, once its six gliders
+        // are gone.
+        let mut world = board(160, 160, Topology::Plane, "x = 3, y = 3\nb2o$2o$bo!");
+        let stable = world.run_until_stable(2000).unwrap();
+        assert!(stable.generation >= 1103);
+        assert_eq!(stable.period, 2);
+        let escaped: Vec<_> = stable
+            .escaped
+            .iter()
+            .map(|cells| apgcode::encode(cells, world.rule()).unwrap())
+            .collect();
+        assert_eq!(escaped, vec!["xq4_153"; 6]);
+        // Out of 116 cells with the gliders.
+        assert_eq!(world.population(), 116 - 6 * 5);
+
+        let mut world = board(160, 160, Topology::Plane, "x = 3, y = 3\nb2o$2o$bo!");
+        assert_eq!(world.run_until_stable(100), None);
+        assert_eq!(world.generations(), 100);
+    }
+}
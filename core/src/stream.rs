@@ -0,0 +1,606 @@
+//! Incremental RLE parsing.
+//!
+//! `parser::parse_rle` needs the whole pattern in memory and builds every run
+//! before anything is placed. `RleStream` instead reads the header up front,
+//! then yields runs one at a time while pulling bytes from a `BufRead`, so
+//! memory use stays bounded by the reader's buffer whatever the file size.
+//!
+//! In `Mode::Lenient`, the stream also accepts the sloppier files found in
+//! the wild and records a `Warning` for each kind of problem it let through.
+
+use std::fmt;
+use std::io::{self, BufRead, Read};
+
+use super::parser::{
+    multi_state, rle_comment, rle_first_line, RleComment, RleFirstLine, RleTag, RleTagSequence,
+};
+use super::{Error, Result, World};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// Only accepts well formed patterns.
+    Strict,
+    /// Accepts CRLF line endings, blank lines and whitespace inside runs, a
+    /// missing `!` and unknown cell letters, which are read as alive.
+    Lenient,
+}
+
+/// Something a lenient stream tolerated. Byte offsets count from the start
+/// of the input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Warning {
+    CrLf,
+    StrayWhitespace(u64),
+    UnknownCell(char, u64),
+    MissingEnd,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Warning::CrLf => write!(f, "CRLF line endings"),
+            Warning::StrayWhitespace(byte) => write!(f, "stray whitespace at byte {}", byte),
+            Warning::UnknownCell(c, byte) => {
+                write!(f, "unknown cell {:?} at byte {}, read as alive", c, byte)
+            }
+            Warning::MissingEnd => write!(f, "missing `!` at the end of the pattern"),
+        }
+    }
+}
+
+pub struct RleStream<R> {
+    reader: R,
+    mode: Mode,
+    comments: Vec<RleComment>,
+    size: RleFirstLine,
+    rule: Option<String>,
+    warnings: Vec<Warning>,
+    bytes_read: u64,
+    count: Option<usize>,
+    states: usize,
+    // The `p` to `y` of a multi-state cell waiting for its letter.
+    prefix: Option<u8>,
+    done: bool,
+}
+
+impl<R: BufRead> RleStream<R> {
+    /// Reads the comment lines and the `x = .., y = ..` line, leaving the
+    /// cells to the iterator.
+    pub fn new(reader: R) -> Result<RleStream<R>> {
+        RleStream::with_mode(reader, Mode::Strict)
+    }
+
+    pub fn lenient(reader: R) -> Result<RleStream<R>> {
+        RleStream::with_mode(reader, Mode::Lenient)
+    }
+
+    pub fn with_mode(reader: R, mode: Mode) -> Result<RleStream<R>> {
+        let mut stream = RleStream {
+            reader,
+            mode,
+            comments: Vec::new(),
+            size: RleFirstLine(0, 0),
+            rule: None,
+            warnings: Vec::new(),
+            bytes_read: 0,
+            count: None,
+            states: 2,
+            prefix: None,
+            done: false,
+        };
+        stream.read_header()?;
+        Ok(stream)
+    }
+
+    fn read_header(&mut self) -> Result<()> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let n = self.reader.read_line(&mut line).map_err(io_error)?;
+            if n == 0 {
+                return Err(Error::Parse("missing size line".to_string()));
+            }
+            let start = self.bytes_read;
+            self.bytes_read += n as u64;
+            if self.mode == Mode::Lenient {
+                if line.ends_with("\r\n") {
+                    line.truncate(line.len() - 2);
+                    self.warn(Warning::CrLf);
+                }
+                if line.trim().is_empty() {
+                    self.warn(Warning::StrayWhitespace(start));
+                    continue;
+                }
+            }
+            if !line.ends_with('\n') {
+                line.push('\n');
+            }
+
+            if let Ok((_, comment)) = rle_comment(line.as_str().into()) {
+                if let RleComment::Rule(ref rule) = comment {
+                    self.rule = Some(rule.trim().to_string());
+                }
+                self.comments.push(comment);
+                continue;
+            }
+            match rle_first_line(line.as_str().into()) {
+                Ok((_, size)) => {
+                    self.size = size;
+                    if let Some(rule) = header_rule(&line) {
+                        self.rule = Some(rule);
+                    }
+                    return Ok(());
+                }
+                Err(_) => {
+                    return Err(Error::Parse(format!(
+                        "invalid header line: {}",
+                        line.trim()
+                    )))
+                }
+            }
+        }
+    }
+
+    /// Records `warning`, unless a warning of the same kind (and letter, for
+    /// unknown cells) was already recorded.
+    fn warn(&mut self, warning: Warning) {
+        let seen = self.warnings.iter().any(|w| match (w, &warning) {
+            (Warning::UnknownCell(a, _), Warning::UnknownCell(b, _)) => a == b,
+            (a, b) => ::std::mem::discriminant(a) == ::std::mem::discriminant(b),
+        });
+        if !seen {
+            self.warnings.push(warning);
+        }
+    }
+
+    pub fn comments(&self) -> &[RleComment] {
+        &self.comments
+    }
+
+    /// The rule given by the header line, or by a `#r` comment.
+    pub fn rule(&self) -> Option<&str> {
+        self.rule.as_deref()
+    }
+
+    /// What was tolerated so far. Each kind of problem is reported once,
+    /// at its first occurrence.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// The pattern size announced by the header.
+    pub fn size(&self) -> &RleFirstLine {
+        &self.size
+    }
+
+    /// Sets how many cell states the pattern may use, two by default. Only
+    /// patterns with more states have `A` to `X` and `pA` to `yO` cells, and
+    /// only up to their last state; other letters are unknown cells.
+    pub fn set_states(&mut self, states: usize) {
+        self.states = states;
+    }
+
+    /// The multi-state cell written `letter` after `prefix`, if the pattern
+    /// has that state.
+    fn state(&self, prefix: Option<u8>, letter: u8) -> Option<u8> {
+        if self.states <= 2 {
+            return None;
+        }
+        multi_state(prefix.map(char::from), letter as char)
+            .filter(|&state| (state as usize) < self.states)
+    }
+
+    /// Number of bytes consumed from the reader so far, header included.
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    fn fail(&mut self, reason: String) -> Option<Result<RleTagSequence>> {
+        self.done = true;
+        Some(Err(Error::Parse(reason)))
+    }
+}
+
+impl<I, S> RleStream<io::BufReader<ChunkReader<I>>>
+where
+    I: Iterator<Item = S>,
+    S: AsRef<str>,
+{
+    /// Parses a pattern handed over in pieces, e.g. as it is downloaded.
+    /// Chunk boundaries can fall anywhere, even in the middle of a number.
+    pub fn from_chunks(chunks: impl IntoIterator<IntoIter = I>) -> Result<Self> {
+        RleStream::new(io::BufReader::new(ChunkReader::new(chunks.into_iter())))
+    }
+}
+
+impl<R: BufRead> Iterator for RleStream<R> {
+    type Item = Result<RleTagSequence>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        loop {
+            let buf = match self.reader.fill_buf() {
+                Ok(buf) => buf,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(io_error(e)));
+                }
+            };
+            if buf.is_empty() {
+                if self.mode == Mode::Lenient {
+                    self.done = true;
+                    self.warn(Warning::MissingEnd);
+                    return None;
+                }
+                return self.fail("missing `!` at the end of the pattern".to_string());
+            }
+
+            let lenient = self.mode == Mode::Lenient;
+            let mut consumed = 0;
+            let mut run = None;
+            let mut error = None;
+            let mut warning = None;
+            for &byte in buf {
+                consumed += 1;
+                let at = self.bytes_read + consumed as u64 - 1;
+                if let Some(prefix) = self.prefix.take() {
+                    match self.state(Some(prefix), byte) {
+                        Some(state) => {
+                            run = Some(RleTagSequence(
+                                self.count.take().unwrap_or(1),
+                                RleTag::State(state),
+                            ));
+                        }
+                        None if lenient => {
+                            // The letter is read again on its own.
+                            consumed -= 1;
+                            warning = Some(Warning::UnknownCell(prefix as char, at - 1));
+                            run = Some(RleTagSequence(
+                                self.count.take().unwrap_or(1),
+                                RleTag::Alive,
+                            ));
+                        }
+                        None => {
+                            let token = format!("{}{}", prefix as char, byte as char);
+                            error = Some(format!("unexpected {:?} at byte {}", token, at - 1));
+                        }
+                    }
+                    break;
+                }
+                let tag = match byte {
+                    b'0'..=b'9' => {
+                        let digit = (byte - b'0') as usize;
+                        match self.count.unwrap_or(0).checked_mul(10) {
+                            Some(n) => self.count = n.checked_add(digit),
+                            None => self.count = None,
+                        }
+                        if self.count.is_none() {
+                            error = Some("run count overflow".to_string());
+                            break;
+                        }
+                        continue;
+                    }
+                    b'\r' if lenient => {
+                        warning = Some(Warning::CrLf);
+                        break;
+                    }
+                    b' ' | b'\t' | b'\r' | b'\n' if self.count.is_none() => continue,
+                    b' ' | b'\t' | b'\n' if lenient => {
+                        warning = Some(Warning::StrayWhitespace(at));
+                        break;
+                    }
+                    b'!' => {
+                        self.done = true;
+                        break;
+                    }
+                    b'b' | b'.' => RleTag::Dead,
+                    b'o' => RleTag::Alive,
+                    b'$' => RleTag::NextLine,
+                    b'p'..=b'y' if self.states > 2 => {
+                        self.prefix = Some(byte);
+                        continue;
+                    }
+                    b'A'..=b'X' if self.state(None, byte).is_some() => {
+                        RleTag::State(byte - b'A' + 1)
+                    }
+                    c if lenient && c.is_ascii_alphabetic() => {
+                        warning = Some(Warning::UnknownCell(c as char, at));
+                        RleTag::Alive
+                    }
+                    _ => {
+                        error = Some(format!("unexpected {:?} at byte {}", byte as char, at));
+                        break;
+                    }
+                };
+                run = Some(RleTagSequence(self.count.take().unwrap_or(1), tag));
+                break;
+            }
+            self.reader.consume(consumed);
+            self.bytes_read += consumed as u64;
+
+            if let Some(error) = error {
+                return self.fail(error);
+            }
+            if let Some(warning) = warning {
+                self.warn(warning);
+            }
+            if run.is_some() || self.done {
+                return run.map(Ok);
+            }
+        }
+    }
+}
+
+/// Adapts an iterator of string chunks to `Read`, see `RleStream::from_chunks`.
+pub struct ChunkReader<I: Iterator> {
+    chunks: I,
+    current: Option<I::Item>,
+    pos: usize,
+}
+
+impl<I, S> ChunkReader<I>
+where
+    I: Iterator<Item = S>,
+    S: AsRef<str>,
+{
+    pub fn new(chunks: I) -> ChunkReader<I> {
+        ChunkReader {
+            chunks,
+            current: None,
+            pos: 0,
+        }
+    }
+}
+
+impl<I, S> Read for ChunkReader<I>
+where
+    I: Iterator<Item = S>,
+    S: AsRef<str>,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if let Some(ref chunk) = self.current {
+                let rest = &chunk.as_ref().as_bytes()[self.pos..];
+                if !rest.is_empty() {
+                    let n = ::std::cmp::min(rest.len(), buf.len());
+                    buf[..n].copy_from_slice(&rest[..n]);
+                    self.pos += n;
+                    return Ok(n);
+                }
+            }
+            match self.chunks.next() {
+                Some(chunk) => {
+                    self.current = Some(chunk);
+                    self.pos = 0;
+                }
+                None => return Ok(0),
+            }
+        }
+    }
+}
+
+/// The `rule = ..` part of a size line.
+fn header_rule(line: &str) -> Option<String> {
+    line.split(',').find_map(|part| {
+        let mut kv = part.splitn(2, '=');
+        match (kv.next(), kv.next()) {
+            (Some(key), Some(value)) if key.trim() == "rule" => Some(value.trim().to_string()),
+            _ => None,
+        }
+    })
+}
+
+fn io_error(e: io::Error) -> Error {
+    Error::Parse(e.to_string())
+}
+
+impl World {
+    /// Places the pattern read by `stream` like `load_rle` does, without
+    /// keeping its runs around. `progress` is called with the number of
+    /// bytes read after each row and once the pattern is complete.
+    ///
+    /// On error, the rows read so far stay on the board.
+    pub fn load_rle_stream<R: BufRead>(
+        &mut self,
+        stream: &mut RleStream<R>,
+        mut progress: impl FnMut(u64),
+    ) -> Result<()> {
+        stream.set_states(self.state_count());
+        let top_left = self.rle_top_left(stream.comments(), stream.size());
+        let mut cursor = top_left;
+        while let Some(run) = stream.next() {
+            let run = run?;
+            let end_of_row = run.1 == RleTag::NextLine;
+            self.place_run(top_left, &mut cursor, run)?;
+            if end_of_row {
+                progress(stream.bytes_read());
+            }
+        }
+        progress(stream.bytes_read());
+        Ok(())
+    }
+
+    /// Like `load_rle_str`, but in `Mode::Lenient`, returning what had to be
+    /// tolerated.
+    pub fn load_rle_lenient(&mut self, pattern: &str) -> Result<Vec<Warning>> {
+        let mut stream = RleStream::lenient(pattern.as_bytes())?;
+        self.load_rle_stream(&mut stream, |_| {})?;
+        Ok(stream.warnings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_rle;
+
+    const GLIDER: &str = "#N Glider\n#O Richard K. Guy\nx = 3, y = 3, rule = B3/S23\nbob$2bo$3o!\n";
+
+    #[test]
+    fn stream_header_test() {
+        let stream = RleStream::new(GLIDER.as_bytes()).unwrap();
+        assert_eq!(stream.size(), &RleFirstLine(3, 3));
+        assert_eq!(
+            stream.comments(),
+            &[
+                RleComment::Name("Glider".to_string()),
+                RleComment::Author("Richard K. Guy".to_string())
+            ][..]
+        );
+    }
+
+    #[test]
+    fn stream_matches_parse_rle_test() {
+        use std::fs;
+
+        let patterns = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../patterns");
+        for entry in fs::read_dir(patterns).unwrap() {
+            let path = entry.unwrap().path();
+            let content = fs::read_to_string(&path).unwrap();
+            let (_, rle) = parse_rle(content.as_str().into()).unwrap();
+
+            let stream = RleStream::new(content.as_bytes()).unwrap();
+            assert_eq!(stream.size(), &rle.size, "{:?}", path);
+            assert_eq!(stream.comments(), &rle.comments[..], "{:?}", path);
+            let runs: Vec<_> = stream.map(|run| run.unwrap()).collect();
+            assert_eq!(runs, rle.content, "{:?}", path);
+        }
+    }
+
+    #[test]
+    fn stream_from_chunks_test() {
+        let stream = RleStream::from_chunks(vec!["x = 12, y", " = 1\n1", "", "2o", "!"]).unwrap();
+        let runs: Vec<_> = stream.map(|run| run.unwrap()).collect();
+        assert_eq!(runs, vec![RleTagSequence(12, RleTag::Alive)]);
+    }
+
+    #[test]
+    fn stream_errors_test() {
+        let runs: Vec<_> = RleStream::new("x = 2, y = 1\n2o".as_bytes())
+            .unwrap()
+            .collect();
+        assert_eq!(runs.len(), 2);
+        assert!(runs[1].is_err());
+
+        let runs: Vec<_> = RleStream::new("x = 2, y = 1\nbxo!".as_bytes())
+            .unwrap()
+            .collect();
+        match &runs[..] {
+            [Ok(_), Err(Error::Parse(e))] => assert!(e.contains("at byte 14")),
+            _ => panic!("unexpected runs {:?}", runs),
+        }
+
+        assert!(RleStream::new("bo$2bo$3o!".as_bytes()).is_err());
+        assert!(RleStream::new("".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn load_rle_stream_test() {
+        let mut streamed = World::new(16, 16);
+        let mut progress = Vec::new();
+        let mut stream =
+            RleStream::new(io::BufReader::with_capacity(4, GLIDER.as_bytes())).unwrap();
+        streamed
+            .load_rle_stream(&mut stream, |bytes| progress.push(bytes))
+            .unwrap();
+
+        let mut world = World::new(16, 16);
+        world.load_rle_str(GLIDER).unwrap();
+        assert_eq!(streamed.cells(), world.cells());
+
+        assert_eq!(progress.len(), 3);
+        assert!(progress.windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(*progress.last().unwrap(), GLIDER.len() as u64 - 1);
+    }
+
+    #[test]
+    fn rule_test() {
+        let stream = RleStream::new(GLIDER.as_bytes()).unwrap();
+        assert_eq!(stream.rule(), Some("B3/S23"));
+
+        let stream = RleStream::new("#r 23/36\nx = 1, y = 1\no!".as_bytes()).unwrap();
+        assert_eq!(stream.rule(), Some("23/36"));
+        assert_eq!(
+            stream.comments(),
+            &[RleComment::Rule("23/36".to_string())][..]
+        );
+
+        let stream = RleStream::new("x = 1, y = 1\no!".as_bytes()).unwrap();
+        assert_eq!(stream.rule(), None);
+    }
+
+    #[test]
+    fn lenient_test() {
+        let messy = "#N Glider\r\n\r\nx = 3, y = 3, rule = B3/S23\r\nbo\r\nb$2 bo$ 3Z\r\n";
+        assert!(RleStream::new(messy.as_bytes()).is_err());
+
+        let mut world = World::new(16, 16);
+        let warnings = world.load_rle_lenient(messy).unwrap();
+        let mut glider = World::new(16, 16);
+        glider.load_rle_str(GLIDER).unwrap();
+        assert_eq!(world.cells(), glider.cells());
+        assert_eq!(
+            warnings,
+            vec![
+                Warning::CrLf,
+                Warning::StrayWhitespace(11),
+                Warning::UnknownCell('Z', 55),
+                Warning::MissingEnd,
+            ]
+        );
+
+        // Still an error: not a cell letter.
+        assert!(world.load_rle_lenient("x = 1, y = 1\n2o?!").is_err());
+    }
+
+    #[test]
+    fn state_letters_test() {
+        // Multi-state letters are unknown cells under a two-state rule.
+        for &(cells, letter) in &[("ox!", 'x'), ("op!", 'p'), ("oA!", 'A')] {
+            let pattern = format!("x = 2, y = 1\n{}", cells);
+            let mut world = World::new(8, 8);
+            assert!(world.load_rle_str(&pattern).is_err(), "{}", cells);
+            let mut stream = RleStream::new(pattern.as_bytes()).unwrap();
+            assert!(
+                world.load_rle_stream(&mut stream, |_| {}).is_err(),
+                "{}",
+                cells
+            );
+
+            let mut world = World::new(8, 8);
+            let warnings = world.load_rle_lenient(&pattern).unwrap();
+            assert_eq!(
+                warnings,
+                vec![Warning::UnknownCell(letter, 14)],
+                "{}",
+                cells
+            );
+            assert_eq!(world.population(), 2, "{}", cells);
+        }
+
+        // WireWorld only has states up to C.
+        let wires = "x = 6, y = 1\nABD.pA!";
+        let mut world = World::new(8, 8);
+        world.set_automaton(Some(crate::Automaton::WireWorld));
+        assert!(world.load_rle_str(wires).is_err());
+        assert!(world.load_rle_str("x = 2, y = 1\nApA!").is_err());
+        assert_eq!(world.population(), 0);
+
+        let warnings = world.load_rle_lenient(wires).unwrap();
+        assert_eq!(
+            warnings,
+            vec![Warning::UnknownCell('D', 15), Warning::UnknownCell('p', 17)]
+        );
+        let states: Vec<u8> = world.states().filter(|&state| state != 0).collect();
+        assert_eq!(states, vec![1, 2, 1, 1, 1]);
+    }
+
+    #[test]
+    fn lenient_header_without_newline_test() {
+        let mut stream = RleStream::lenient("x = 0, y = 0".as_bytes()).unwrap();
+        assert!(stream.next().is_none());
+        assert_eq!(stream.warnings(), &[Warning::MissingEnd][..]);
+    }
+}
@@ -0,0 +1,672 @@
+//! Golly rule tables.
+//!
+//! A `.rule` file names a many-state automaton (`@RULE`), lists its
+//! transitions (`@TABLE`) and optionally the colors of its states
+//! (`@COLORS`). Other sections are ignored, except `@TREE` which is not
+//! supported.
+//!
+//! Transitions are matched in the order of the file, the first match wins
+//! and cells matching no transition keep their state. Variables are bound:
+//! a variable used twice in a transition stands for the same state both
+//! times. Results are memoized per neighborhood, so a table is only scanned
+//! once for each configuration met on the board.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use super::{Error, Result};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Neighborhood {
+    /// The eight surrounding cells, listed N, NE, E, SE, S, SW, W, NW.
+    Moore,
+    /// The four orthogonal neighbors, listed N, E, S, W.
+    VonNeumann,
+}
+
+impl Neighborhood {
+    pub fn size(self) -> usize {
+        match self {
+            Neighborhood::Moore => 8,
+            Neighborhood::VonNeumann => 4,
+        }
+    }
+
+    /// `(row, col)` offsets of the neighbors, in table order.
+    pub fn offsets(self) -> &'static [(i32, i32)] {
+        match self {
+            Neighborhood::Moore => &[
+                (-1, 0),
+                (-1, 1),
+                (0, 1),
+                (1, 1),
+                (1, 0),
+                (1, -1),
+                (0, -1),
+                (-1, -1),
+            ],
+            Neighborhood::VonNeumann => &[(-1, 0), (0, 1), (1, 0), (0, -1)],
+        }
+    }
+}
+
+/// How the neighbors listed by a transition may be rearranged.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Symmetries {
+    /// These permutations of the neighbors, the identity included.
+    Permutations(Vec<Vec<usize>>),
+    /// Any permutation: only the neighbor counts matter.
+    Any,
+}
+
+impl Symmetries {
+    fn new(name: &str, neighborhood: Neighborhood) -> Option<Symmetries> {
+        let n = neighborhood.size();
+        let (quarter_turn, eighth_turn) = match neighborhood {
+            Neighborhood::Moore => (2, Some(1)),
+            Neighborhood::VonNeumann => (1, None),
+        };
+        let rotations = |step: usize| -> Vec<Vec<usize>> {
+            (0..n / step)
+                .map(|k| (0..n).map(|i| (i + k * step) % n).collect())
+                .collect()
+        };
+        let reflected = |permutations: Vec<Vec<usize>>| -> Vec<Vec<usize>> {
+            let mirror: Vec<usize> = (0..n).map(|i| (n - i) % n).collect();
+            let mut all = permutations.clone();
+            all.extend(
+                permutations
+                    .iter()
+                    .map(|p| mirror.iter().map(|&i| p[i]).collect()),
+            );
+            all
+        };
+        Some(Symmetries::Permutations(match name {
+            "none" => rotations(n),
+            "rotate4" => rotations(quarter_turn),
+            "rotate8" => rotations(eighth_turn?),
+            "rotate4reflect" => reflected(rotations(quarter_turn)),
+            "rotate8reflect" => reflected(rotations(eighth_turn?)),
+            "reflect_horizontal" => reflected(rotations(n)),
+            "permute" => return Some(Symmetries::Any),
+            _ => return None,
+        }))
+    }
+}
+
+/// The states allowed at one position of a transition.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Input {
+    states: [u64; 4],
+    /// Inputs sharing a variable must have the same state.
+    var: Option<usize>,
+}
+
+impl Input {
+    fn allows(&self, state: u8) -> bool {
+        self.states[state as usize / 64] & (1 << (state % 64)) != 0
+    }
+
+    /// Whether `state` fits, binding the variable if it's the first
+    /// occurrence.
+    fn bind(&self, state: u8, bound: &mut [Option<u8>]) -> bool {
+        if !self.allows(state) {
+            return false;
+        }
+        match self.var {
+            Some(var) => match bound[var] {
+                Some(b) => b == state,
+                None => {
+                    bound[var] = Some(state);
+                    true
+                }
+            },
+            None => true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Output {
+    State(u8),
+    Var(usize),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Transition {
+    center: Input,
+    neighbors: Vec<Input>,
+    output: Output,
+    vars: usize,
+}
+
+impl Transition {
+    fn apply(&self, center: u8, neighbors: &[u8], symmetries: &Symmetries) -> Option<u8> {
+        let mut bound = vec![None; self.vars];
+        if !self.center.bind(center, &mut bound) {
+            return None;
+        }
+        let bound = match symmetries {
+            Symmetries::Permutations(permutations) => permutations.iter().find_map(|p| {
+                let mut bound = bound.clone();
+                let matches = self
+                    .neighbors
+                    .iter()
+                    .zip(p.iter())
+                    .all(|(input, &i)| input.bind(neighbors[i], &mut bound));
+                Some(bound).filter(|_| matches)
+            }),
+            Symmetries::Any => self.assign(neighbors, 0, &mut vec![false; neighbors.len()], bound),
+        }?;
+        Some(match self.output {
+            Output::State(state) => state,
+            Output::Var(var) => bound[var].expect("Output variables are bound by an input"),
+        })
+    }
+
+    /// Matches the inputs from `input` on with distinct unused neighbors.
+    fn assign(
+        &self,
+        neighbors: &[u8],
+        input: usize,
+        used: &mut [bool],
+        bound: Vec<Option<u8>>,
+    ) -> Option<Vec<Option<u8>>> {
+        if input == self.neighbors.len() {
+            return Some(bound);
+        }
+        for i in 0..neighbors.len() {
+            if used[i] {
+                continue;
+            }
+            let mut candidate = bound.clone();
+            if self.neighbors[input].bind(neighbors[i], &mut candidate) {
+                used[i] = true;
+                let found = self.assign(neighbors, input + 1, used, candidate);
+                used[i] = false;
+                if found.is_some() {
+                    return found;
+                }
+            }
+        }
+        None
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RuleTable {
+    name: String,
+    states: usize,
+    neighborhood: Neighborhood,
+    symmetries: Symmetries,
+    transitions: Vec<Transition>,
+    colors: Vec<Option<u32>>,
+    memo: HashMap<[u8; 9], u8>,
+    source: String,
+}
+
+impl PartialEq for RuleTable {
+    fn eq(&self, other: &RuleTable) -> bool {
+        self.name == other.name
+            && self.states == other.states
+            && self.neighborhood == other.neighborhood
+            && self.symmetries == other.symmetries
+            && self.transitions == other.transitions
+            && self.colors == other.colors
+    }
+}
+
+impl RuleTable {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Number of states, state 0 being the background.
+    pub fn states(&self) -> usize {
+        self.states
+    }
+
+    pub fn neighborhood(&self) -> Neighborhood {
+        self.neighborhood
+    }
+
+    /// The text the table was parsed from.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The `0xRRGGBB` color given by `@COLORS` to `state`, if any.
+    pub fn color(&self, state: u8) -> Option<u32> {
+        self.colors.get(state as usize).copied().flatten()
+    }
+
+    /// The next state of a cell in state `center`, given the states of its
+    /// neighbors in the order of `Neighborhood::offsets`.
+    pub fn next_state(&mut self, center: u8, neighbors: &[u8]) -> u8 {
+        let mut key = [0; 9];
+        key[0] = center;
+        key[1..=neighbors.len()].copy_from_slice(neighbors);
+        if let Some(&state) = self.memo.get(&key) {
+            return state;
+        }
+        let state = self
+            .transitions
+            .iter()
+            .find_map(|t| t.apply(center, neighbors, &self.symmetries))
+            .unwrap_or(center);
+        self.memo.insert(key, state);
+        state
+    }
+}
+
+struct Parser {
+    line: usize,
+    states: usize,
+    neighborhood: Neighborhood,
+    symmetries: Option<Symmetries>,
+    vars: HashMap<String, Vec<u8>>,
+}
+
+impl Parser {
+    fn error(&self, message: impl fmt::Display) -> Error {
+        Error::InvalidRuleTable(format!("line {}: {}", self.line, message))
+    }
+
+    fn state(&self, token: &str) -> Result<u8> {
+        match token.parse::<usize>() {
+            Ok(state) if state < self.states => Ok(state as u8),
+            _ => Err(self.error(format_args!("invalid state {}", token))),
+        }
+    }
+
+    /// The states of `{a,1,2}`, where elements are states or variables.
+    fn set(&self, token: &str) -> Result<Vec<u8>> {
+        let inner = token
+            .strip_prefix('{')
+            .and_then(|t| t.strip_suffix('}'))
+            .ok_or_else(|| self.error(format_args!("invalid set {}", token)))?;
+        let mut states = Vec::new();
+        for element in inner.split(',').map(str::trim) {
+            match self.vars.get(element) {
+                Some(values) => states.extend_from_slice(values),
+                None => states.push(self.state(element)?),
+            }
+        }
+        Ok(states)
+    }
+
+    fn header(&mut self, key: &str, value: &str) -> Result<()> {
+        match key {
+            "n_states" | "num_states" => match value.parse() {
+                Ok(states) if (2..=256).contains(&states) => self.states = states,
+                _ => return Err(self.error(format_args!("invalid number of states {}", value))),
+            },
+            "neighborhood" => {
+                self.neighborhood = match value {
+                    "Moore" => Neighborhood::Moore,
+                    "vonNeumann" => Neighborhood::VonNeumann,
+                    _ => return Err(self.error(format_args!("unsupported neighborhood {}", value))),
+                }
+            }
+            "symmetries" => {
+                let symmetries = Symmetries::new(value, self.neighborhood)
+                    .ok_or_else(|| self.error(format_args!("unsupported symmetries {}", value)))?;
+                self.symmetries = Some(symmetries);
+            }
+            _ => return Err(self.error(format_args!("unknown setting {}", key))),
+        }
+        Ok(())
+    }
+
+    fn transition(&self, line: &str) -> Result<Transition> {
+        let tokens = split_tokens(line);
+        let expected = self.neighborhood.size() + 2;
+        if tokens.len() != expected {
+            return Err(self.error(format_args!(
+                "expected {} states, found {}",
+                expected,
+                tokens.len()
+            )));
+        }
+
+        let mut var_ids: Vec<&str> = Vec::new();
+        let mut inputs = Vec::new();
+        for token in &tokens[..expected - 1] {
+            let (states, var) = if token.starts_with('{') {
+                (self.set(token)?, None)
+            } else if let Some(values) = self.vars.get(*token) {
+                let id = match var_ids.iter().position(|v| v == token) {
+                    Some(id) => id,
+                    None => {
+                        var_ids.push(token);
+                        var_ids.len() - 1
+                    }
+                };
+                (values.clone(), Some(id))
+            } else {
+                (vec![self.state(token)?], None)
+            };
+            let mut set = [0; 4];
+            for state in states {
+                set[state as usize / 64] |= 1 << (state % 64);
+            }
+            inputs.push(Input { states: set, var });
+        }
+
+        let last = tokens[expected - 1];
+        let output = match var_ids.iter().position(|v| *v == last) {
+            Some(id) => Output::Var(id),
+            None if self.vars.contains_key(last) => {
+                return Err(self.error(format_args!("unbound output variable {}", last)))
+            }
+            None => Output::State(self.state(last)?),
+        };
+        let center = inputs.remove(0);
+        Ok(Transition {
+            center,
+            neighbors: inputs,
+            output,
+            vars: var_ids.len(),
+        })
+    }
+}
+
+/// Splits a transition on commas outside of braces, or into single digits
+/// in the compact form without commas.
+fn split_tokens(line: &str) -> Vec<&str> {
+    if !line.contains(',') {
+        return line
+            .char_indices()
+            .filter(|(_, c)| !c.is_whitespace())
+            .map(|(i, c)| &line[i..i + c.len_utf8()])
+            .collect();
+    }
+    let mut tokens = Vec::new();
+    let (mut depth, mut start) = (0, 0);
+    for (i, c) in line.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                tokens.push(line[start..i].trim());
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+    tokens.push(line[start..].trim());
+    tokens
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Section {
+    Rule,
+    Table,
+    Colors,
+    Other,
+}
+
+impl FromStr for RuleTable {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<RuleTable> {
+        let mut parser = Parser {
+            line: 0,
+            states: 2,
+            neighborhood: Neighborhood::Moore,
+            symmetries: None,
+            vars: HashMap::new(),
+        };
+        let mut name = None;
+        let mut section = Section::Other;
+        let mut has_table = false;
+        let mut transitions = Vec::new();
+        let mut colors = Vec::new();
+
+        for (number, line) in s.lines().enumerate() {
+            parser.line = number + 1;
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(header) = line.strip_prefix('@') {
+                let mut words = header.split_whitespace();
+                section = match words.next() {
+                    Some("RULE") => {
+                        name = words.next().map(str::to_string);
+                        Section::Rule
+                    }
+                    Some("TABLE") => {
+                        has_table = true;
+                        Section::Table
+                    }
+                    Some("COLORS") => Section::Colors,
+                    Some("TREE") => return Err(parser.error("rule trees are not supported")),
+                    _ => Section::Other,
+                };
+                continue;
+            }
+
+            match section {
+                Section::Table => {
+                    if let Some(definition) = line.strip_prefix("var ") {
+                        let (var, set) = definition
+                            .split_once('=')
+                            .ok_or_else(|| parser.error("invalid variable"))?;
+                        let set = parser.set(set.trim())?;
+                        parser.vars.insert(var.trim().to_string(), set);
+                    } else if let Some((key, value)) = line.split_once(':') {
+                        parser.header(key.trim(), value.trim())?;
+                    } else {
+                        transitions.push(parser.transition(line)?);
+                    }
+                }
+                Section::Colors => {
+                    let numbers = line
+                        .split_whitespace()
+                        .map(|n| n.parse::<u32>())
+                        .collect::<std::result::Result<Vec<_>, _>>()
+                        .map_err(|_| parser.error("invalid color"))?;
+                    colors.push(numbers);
+                }
+                Section::Rule | Section::Other => (),
+            }
+        }
+
+        let name = name.ok_or_else(|| Error::InvalidRuleTable("missing @RULE".to_string()))?;
+        if !has_table {
+            return Err(Error::InvalidRuleTable("missing @TABLE".to_string()));
+        }
+        let (states, neighborhood) = (parser.states, parser.neighborhood);
+        Ok(RuleTable {
+            name,
+            states,
+            neighborhood,
+            symmetries: parser
+                .symmetries
+                .unwrap_or_else(|| Symmetries::new("none", neighborhood).unwrap()),
+            transitions,
+            colors: palette(&colors, states),
+            memo: HashMap::new(),
+            source: s.to_string(),
+        })
+    }
+}
+
+/// Colors of each state from the `@COLORS` lines: `state r g b`, or
+/// `r g b r g b` for a gradient over the live states.
+fn palette(lines: &[Vec<u32>], states: usize) -> Vec<Option<u32>> {
+    let rgb = |c: &[u32]| (c[0].min(255) << 16) | (c[1].min(255) << 8) | c[2].min(255);
+    let mut colors = vec![None; states];
+    for line in lines {
+        match line.len() {
+            4 if (line[0] as usize) < states => colors[line[0] as usize] = Some(rgb(&line[1..])),
+            6 => {
+                for (state, color) in colors.iter_mut().enumerate().skip(1) {
+                    let (step, steps) = (state as u32 - 1, (states as u32 - 2).max(1));
+                    let channel = |i: usize| {
+                        let (from, to) = (line[i] as i64, line[i + 3] as i64);
+                        (from + (to - from) * i64::from(step) / i64::from(steps)) as u32
+                    };
+                    *color = Some(rgb(&[channel(0), channel(1), channel(2)]));
+                }
+            }
+            _ => (),
+        }
+    }
+    colors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIREWORLD: &str = "@RULE WireWorld
+# Empty, head, tail and conductor.
+@TABLE
+n_states:4
+neighborhood:Moore
+symmetries:permute
+var a={0,1,2,3}
+var b={0,1,2,3}
+var c={0,1,2,3}
+var d={0,1,2,3}
+var e={0,1,2,3}
+var f={0,1,2,3}
+var g={0,2,3}
+var h={0,2,3}
+var i={0,2,3}
+var j={0,2,3}
+var k={0,2,3}
+var l={0,2,3}
+var m={0,2,3}
+1,a,b,c,d,e,f,g,h,2
+2,a,b,c,d,e,f,g,h,3
+3,1,g,h,i,j,k,l,m,1
+3,1,1,g,h,i,j,k,l,1
+@COLORS
+0 48 48 48
+1 0 128 255
+2 255 255 255
+3 255 128 0
+";
+
+    #[test]
+    fn wireworld_test() {
+        let mut table: RuleTable = WIREWORLD.parse().unwrap();
+        assert_eq!(table.name(), "WireWorld");
+        assert_eq!(table.states(), 4);
+        assert_eq!(table.neighborhood(), Neighborhood::Moore);
+        assert_eq!(table.color(1), Some(0x00_80_FF));
+
+        assert_eq!(table.next_state(1, &[0; 8]), 2);
+        assert_eq!(table.next_state(2, &[3, 1, 0, 0, 0, 0, 0, 0]), 3);
+        // Conductors with one or two heads around become heads, in any
+        // position.
+        assert_eq!(table.next_state(3, &[0, 0, 0, 0, 0, 1, 0, 0]), 1);
+        assert_eq!(table.next_state(3, &[0, 1, 0, 0, 3, 1, 0, 0]), 1);
+        assert_eq!(table.next_state(3, &[1, 1, 1, 0, 0, 0, 0, 0]), 3);
+        assert_eq!(table.next_state(0, &[1, 1, 1, 0, 0, 0, 0, 0]), 0);
+    }
+
+    #[test]
+    fn symmetries_test() {
+        // A cell turns on when only its north neighbor is on, in any
+        // rotation for rotate4.
+        let table = |symmetries: &str| -> RuleTable {
+            format!(
+                "@RULE test\n@TABLE\nn_states:2\nneighborhood:vonNeumann\nsymmetries:{}\n0,1,0,0,0,1\n",
+                symmetries
+            )
+            .parse()
+            .unwrap()
+        };
+        let mut none = table("none");
+        assert_eq!(none.next_state(0, &[1, 0, 0, 0]), 1);
+        assert_eq!(none.next_state(0, &[0, 1, 0, 0]), 0);
+        let mut rotate4 = table("rotate4");
+        assert_eq!(rotate4.next_state(0, &[0, 0, 0, 1]), 1);
+        assert_eq!(rotate4.next_state(0, &[1, 1, 0, 0]), 0);
+
+        // Compact form, and reflections of a transition with an east/west
+        // asymmetry.
+        let mut reflect: RuleTable =
+            "@RULE r\n@TABLE\nn_states:3\nneighborhood:Moore\nsymmetries:reflect_horizontal\n0120000000\n"
+                .parse()
+                .unwrap();
+        assert_eq!(reflect.next_state(0, &[1, 2, 0, 0, 0, 0, 0, 0]), 0);
+        assert_eq!(reflect.next_state(0, &[1, 0, 0, 0, 0, 0, 0, 2]), 0);
+        let mut reflect: RuleTable =
+            "@RULE r\n@TABLE\nn_states:3\nneighborhood:Moore\nsymmetries:reflect_horizontal\n0,1,2,0,0,0,0,0,0,2\n"
+                .parse()
+                .unwrap();
+        assert_eq!(reflect.next_state(0, &[1, 2, 0, 0, 0, 0, 0, 0]), 2);
+        assert_eq!(reflect.next_state(0, &[1, 0, 0, 0, 0, 0, 0, 2]), 2);
+        assert_eq!(reflect.next_state(0, &[1, 0, 2, 0, 0, 0, 0, 0]), 0);
+    }
+
+    #[test]
+    fn variables_test() {
+        // Bound variables: the output copies the state of the neighbors when
+        // north and south agree.
+        let mut table: RuleTable = "@RULE copy
+@TABLE
+n_states:3
+neighborhood:vonNeumann
+var a={1,2}
+var b={0,1,2}
+var c={b}
+0,a,b,a,c,a
+"
+        .parse()
+        .unwrap();
+        assert_eq!(table.next_state(0, &[2, 0, 2, 1]), 2);
+        assert_eq!(table.next_state(0, &[1, 2, 1, 0]), 1);
+        assert_eq!(table.next_state(0, &[1, 0, 2, 0]), 0);
+        // The first matching transition wins, unmatched cells stay.
+        assert_eq!(table.next_state(2, &[1, 0, 1, 0]), 2);
+    }
+
+    #[test]
+    fn colors_test() {
+        let table: RuleTable = "@RULE g\n@TABLE\nn_states:5\n@COLORS\n0 0 0 0\n0 0 0 255 0 0\n"
+            .parse()
+            .unwrap();
+        assert_eq!(table.color(0), Some(0));
+        assert_eq!(table.color(1), Some(0x00_00_00));
+        assert_eq!(table.color(2), Some(0x55_00_00));
+        assert_eq!(table.color(4), Some(0xFF_00_00));
+        assert_eq!(table.color(9), None);
+    }
+
+    #[test]
+    fn errors_test() {
+        let error = |s: &str| match s.parse::<RuleTable>() {
+            Err(Error::InvalidRuleTable(e)) => e,
+            r => panic!("unexpected result {:?}", r.map(|t| t.name)),
+        };
+        assert_eq!(error("@TABLE\n"), "missing @RULE");
+        assert_eq!(error("@RULE x\n"), "missing @TABLE");
+        assert_eq!(
+            error("@RULE x\n@TREE\n"),
+            "line 2: rule trees are not supported"
+        );
+        assert_eq!(
+            error("@RULE x\n@TABLE\nn_states:2\n0,1,0\n"),
+            "line 4: expected 10 states, found 3"
+        );
+        assert_eq!(
+            error("@RULE x\n@TABLE\nn_states:2\n0,1,0,0,0,0,0,0,0,2\n"),
+            "line 4: invalid state 2"
+        );
+        assert_eq!(
+            error("@RULE x\n@TABLE\nvar a={0,1}\n0,0,0,0,0,0,0,0,0,a\n"),
+            "line 4: unbound output variable a"
+        );
+        assert_eq!(
+            error("@RULE x\n@TABLE\nneighborhood:vonNeumann\nsymmetries:rotate8\n"),
+            "line 4: unsupported symmetries rotate8"
+        );
+    }
+}
@@ -0,0 +1,544 @@
+//! Turmites: ants walking on the board, reading and writing the states of
+//! the cells they visit.
+//!
+//! After the cells of a generation are computed, every ant in turn looks at
+//! the state of its cell, which it reads as a color, and at its own state.
+//! Both select a transition, which gives the color to write, how to turn and
+//! the ant's next state. The ant then moves one cell forward.
+//!
+//! Ants draw with the states of the world, so turmites with more than two
+//! colors need an automaton with as many states; a rule table without
+//! transitions keeps the board still apart from the ants.
+
+use std::fmt;
+use std::iter::Peekable;
+use std::str::{Chars, FromStr};
+
+use super::{Coord, Error, Result, World};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Direction {
+    fn turn(self, turn: Turn) -> Direction {
+        use Direction::*;
+        let quarters = match turn {
+            Turn::Straight => 0,
+            Turn::Right => 1,
+            Turn::Back => 2,
+            Turn::Left => 3,
+        };
+        let directions = [North, East, South, West];
+        directions[(self as usize + quarters) % 4]
+    }
+
+    /// `(row, col)` offset of the cell in front.
+    fn offset(self) -> (i32, i32) {
+        match self {
+            Direction::North => (-1, 0),
+            Direction::East => (0, 1),
+            Direction::South => (1, 0),
+            Direction::West => (0, -1),
+        }
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Direction::North => "north",
+            Direction::East => "east",
+            Direction::South => "south",
+            Direction::West => "west",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Direction {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Direction> {
+        match s.to_lowercase().as_str() {
+            "n" | "north" => Ok(Direction::North),
+            "e" | "east" => Ok(Direction::East),
+            "s" | "south" => Ok(Direction::South),
+            "w" | "west" => Ok(Direction::West),
+            _ => Err(Error::InvalidTurmite(format!("unknown direction {}", s))),
+        }
+    }
+}
+
+/// Turns, numbered as in Golly.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Turn {
+    Straight = 1,
+    Right = 2,
+    Back = 4,
+    Left = 8,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Transition {
+    write: u8,
+    turn: Turn,
+    next: u8,
+}
+
+/// The transition table of a turmite, indexed by state and color.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Turmite {
+    states: usize,
+    colors: usize,
+    table: Vec<Transition>,
+}
+
+impl Turmite {
+    /// Turns right on color 0 and left on color 1, flipping the color.
+    pub fn langtons_ant() -> Turmite {
+        "RL".parse().expect("Langton's ant is a valid turmite")
+    }
+
+    pub fn states(&self) -> usize {
+        self.states
+    }
+
+    pub fn colors(&self) -> usize {
+        self.colors
+    }
+
+    fn transition(&self, state: u8, color: u8) -> Option<Transition> {
+        if color as usize >= self.colors {
+            return None;
+        }
+        self.table
+            .get(state as usize * self.colors + color as usize)
+            .copied()
+    }
+
+    /// One state, with a turn per color: `L`, `R`, `U` for a U-turn or `N`
+    /// for none. Each color is replaced by the next one.
+    fn from_turns(turns: &str) -> Result<Turmite> {
+        let colors = turns.chars().count();
+        if colors == 0 || colors > 256 {
+            return Err(Error::InvalidTurmite(format!("{} colors", colors)));
+        }
+        let table = turns
+            .chars()
+            .enumerate()
+            .map(|(color, turn)| {
+                let turn = match turn {
+                    'N' => Turn::Straight,
+                    'R' => Turn::Right,
+                    'U' => Turn::Back,
+                    'L' => Turn::Left,
+                    c => return Err(Error::InvalidTurmite(format!("unknown turn {:?}", c))),
+                };
+                let write = ((color + 1) % colors) as u8;
+                Ok(Transition {
+                    write,
+                    turn,
+                    next: 0,
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Turmite {
+            states: 1,
+            colors,
+            table,
+        })
+    }
+
+    /// Golly's notation: for each state, for each color, `{write, turn,
+    /// next state}`, turns being 1 for none, 2 for right, 4 for a U-turn and
+    /// 8 for left.
+    fn from_golly(spec: &str) -> Result<Turmite> {
+        let invalid = |reason: &str| Error::InvalidTurmite(reason.to_string());
+        let mut chars = spec.chars().peekable();
+        let states = match parse_nested(&mut chars)? {
+            Nested::List(states) => states,
+            Nested::Number(_) => return Err(invalid("expected a list of states")),
+        };
+        if chars.any(|c| !c.is_whitespace()) {
+            return Err(invalid("text after the turmite"));
+        }
+
+        let mut colors = None;
+        let mut table = Vec::new();
+        for state in states.iter() {
+            let transitions = match state {
+                Nested::List(transitions) if !transitions.is_empty() => transitions,
+                _ => return Err(invalid("expected a list of colors")),
+            };
+            if *colors.get_or_insert(transitions.len()) != transitions.len() {
+                return Err(invalid("states with different numbers of colors"));
+            }
+            for transition in transitions {
+                let numbers = match transition {
+                    Nested::List(numbers) => numbers,
+                    Nested::Number(_) => return Err(invalid("expected a transition")),
+                };
+                let numbers: Vec<u32> = numbers
+                    .iter()
+                    .map(|n| match n {
+                        Nested::Number(n) => Ok(*n),
+                        Nested::List(_) => Err(invalid("expected a number")),
+                    })
+                    .collect::<Result<_>>()?;
+                let (colors, state_count) = (transitions.len() as u32, states.len() as u32);
+                table.push(match numbers[..] {
+                    [write, _, next] if write >= colors || next >= state_count => {
+                        return Err(invalid("no such color or state"));
+                    }
+                    [write, turn, next] => Transition {
+                        write: write as u8,
+                        turn: match turn {
+                            1 => Turn::Straight,
+                            2 => Turn::Right,
+                            4 => Turn::Back,
+                            8 => Turn::Left,
+                            _ => return Err(invalid(&format!("unknown turn {}", turn))),
+                        },
+                        next: next as u8,
+                    },
+                    _ => return Err(invalid("transitions have 3 numbers")),
+                });
+            }
+        }
+        let colors = colors.ok_or_else(|| invalid("no states"))?;
+        if states.len() > 256 || colors > 256 {
+            return Err(invalid("too many states or colors"));
+        }
+        Ok(Turmite {
+            states: states.len(),
+            colors,
+            table,
+        })
+    }
+}
+
+/// Writes the turmite in Golly's notation.
+impl fmt::Display for Turmite {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{{")?;
+        for (state, transitions) in self.table.chunks(self.colors).enumerate() {
+            if state > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{{")?;
+            for (color, t) in transitions.iter().enumerate() {
+                if color > 0 {
+                    write!(f, ",")?;
+                }
+                write!(f, "{{{},{},{}}}", t.write, t.turn as u8, t.next)?;
+            }
+            write!(f, "}}")?;
+        }
+        write!(f, "}}")
+    }
+}
+
+/// Parses a turmite in Golly's notation, such as `{{{1,2,0},{0,8,0}}}` for
+/// Langton's ant, or as a string of turns such as `RL`.
+impl FromStr for Turmite {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Turmite> {
+        let s = s.trim();
+        if s.starts_with('{') {
+            Turmite::from_golly(s)
+        } else {
+            Turmite::from_turns(s)
+        }
+    }
+}
+
+enum Nested {
+    Number(u32),
+    List(Vec<Nested>),
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.peek().is_some_and(|c| c.is_whitespace()) {
+        chars.next();
+    }
+}
+
+fn parse_nested(chars: &mut Peekable<Chars>) -> Result<Nested> {
+    let invalid = |reason: &str| Error::InvalidTurmite(reason.to_string());
+    skip_whitespace(chars);
+    match chars.next() {
+        Some('{') => {
+            let mut items = Vec::new();
+            loop {
+                items.push(parse_nested(chars)?);
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some(',') => continue,
+                    Some('}') => return Ok(Nested::List(items)),
+                    _ => return Err(invalid("expected `,` or `}`")),
+                }
+            }
+        }
+        Some(c) if c.is_ascii_digit() => {
+            let mut n = c.to_digit(10).unwrap_or(0);
+            while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+                chars.next();
+                n = n
+                    .checked_mul(10)
+                    .and_then(|n| n.checked_add(digit))
+                    .ok_or_else(|| invalid("number too large"))?;
+            }
+            Ok(Nested::Number(n))
+        }
+        _ => Err(invalid("expected `{` or a number")),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Ant {
+    turmite: Turmite,
+    position: Coord,
+    direction: Direction,
+    state: u8,
+}
+
+impl Ant {
+    pub fn turmite(&self) -> &Turmite {
+        &self.turmite
+    }
+
+    pub fn position(&self) -> Coord {
+        self.position
+    }
+
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    pub fn state(&self) -> u8 {
+        self.state
+    }
+}
+
+impl World {
+    /// Adds an ant running `turmite` at `at`, in its state 0. Its colors
+    /// must all be states of the world.
+    pub fn add_ant(
+        &mut self,
+        turmite: Turmite,
+        at: impl Into<Coord>,
+        direction: Direction,
+    ) -> Result<()> {
+        if turmite.colors() > self.state_count() {
+            return Err(Error::InvalidTurmite(format!(
+                "{} colors on a world with {} states",
+                turmite.colors(),
+                self.state_count()
+            )));
+        }
+        let at = at.into();
+        let idx = self.get_index(at.row, at.col);
+        let (row, col) = self.to_coords(idx);
+        self.ants.push(Ant {
+            turmite,
+            position: Coord::new(row, col),
+            direction,
+            state: 0,
+        });
+        self.changed_cells.push(idx);
+        Ok(())
+    }
+
+    /// Adds an ant in `state`, as saved in a snapshot.
+    pub(crate) fn restore_ant(
+        &mut self,
+        turmite: Turmite,
+        at: Coord,
+        direction: Direction,
+        state: u8,
+    ) -> Result<()> {
+        if state as usize >= turmite.states() {
+            return Err(Error::InvalidTurmite(format!("no state {}", state)));
+        }
+        self.add_ant(turmite, at, direction)?;
+        if let Some(ant) = self.ants.last_mut() {
+            ant.state = state;
+        }
+        Ok(())
+    }
+
+    /// The ants, in the order they move.
+    pub fn ants(&self) -> &[Ant] {
+        &self.ants
+    }
+
+    pub fn clear_ants(&mut self) {
+        for ant in std::mem::take(&mut self.ants) {
+            let idx = self.get_index(ant.position.row, ant.position.col);
+            self.changed_cells.push(idx);
+        }
+    }
+
+    /// Moves every ant once. An ant on a color its turmite doesn't know
+    /// moves forward without changing anything, and an ant leaving a plane
+    /// is removed.
+    pub(crate) fn move_ants(&mut self) {
+        let mut ants = std::mem::take(&mut self.ants);
+        ants.retain_mut(|ant| {
+            let idx = self.get_index(ant.position.row, ant.position.col);
+            if let Some(t) = ant
+                .turmite
+                .transition(ant.state, self.state_at(idx as usize))
+            {
+                self.put_state(idx, t.write);
+                ant.direction = ant.direction.turn(t.turn);
+                ant.state = t.next;
+            }
+            let (r, c) = ant.direction.offset();
+            let (row, col) = (ant.position.row + r, ant.position.col + c);
+            match self.neighbor_index(row, col) {
+                Some(next) => {
+                    let (row, col) = self.to_coords(next);
+                    ant.position = Coord::new(row, col);
+                    self.changed_cells.push(next);
+                    true
+                }
+                None => false,
+            }
+        });
+        self.ants = ants;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Cell, Topology};
+
+    /// A board where only the ants change cells.
+    fn still_world(width: i32, height: i32) -> World {
+        let mut world = World::new(width, height);
+        world.set_rule("B/S012345678".parse().unwrap());
+        world
+    }
+
+    #[test]
+    fn parse_test() {
+        let golly: Turmite = "{{{1, 2, 0}, {0, 8, 0}}}".parse().unwrap();
+        assert_eq!(golly, Turmite::langtons_ant());
+        let turmite: Turmite = "{{{1,2,1},{0,8,0}},{{1,1,0},{1,1,1}}}".parse().unwrap();
+        assert_eq!((turmite.states(), turmite.colors()), (2, 2));
+        assert_eq!("LLRR".parse::<Turmite>().unwrap().colors(), 4);
+
+        for invalid in &[
+            "",
+            "RX",
+            "{{{1,2,0},{0,8,0}}",
+            "{{{1,2,0},{0,8,0}}} x",
+            "{{{1,3,0},{0,8,0}}}",
+            "{{{2,2,0},{0,8,0}}}",
+            "{{{1,2,1},{0,8,0}}}",
+            "{{{1,2,0},{0,8,0}},{{1,2,0}}}",
+            "{{{1,2},{0,8,0}}}",
+        ] {
+            assert!(invalid.parse::<Turmite>().is_err(), "{:?}", invalid);
+        }
+        assert_eq!("W".parse::<Direction>(), Ok(Direction::West));
+
+        assert_eq!(Turmite::langtons_ant().to_string(), "{{{1,2,0},{0,8,0}}}");
+        assert_eq!(turmite.to_string().parse::<Turmite>().unwrap(), turmite);
+    }
+
+    #[test]
+    fn langtons_ant_test() {
+        let mut world = still_world(12, 12);
+        world
+            .add_ant(Turmite::langtons_ant(), (5, 5), Direction::North)
+            .unwrap();
+        for _ in 0..4 {
+            world.next_tick();
+        }
+        assert_eq!(world.population(), 4);
+        assert_eq!(world.ants()[0].position(), Coord::new(5, 5));
+        assert_eq!(world.ants()[0].direction(), Direction::North);
+
+        world.next_tick();
+        assert_eq!(world.cell((5, 5)), Cell::Dead);
+        assert_eq!(world.ants()[0].position(), Coord::new(5, 4));
+        assert_eq!(world.ants()[0].direction(), Direction::West);
+        assert!(world.changed_cells().contains(&(5 * 12 + 4)));
+    }
+
+    #[test]
+    fn highway_test() {
+        // After about 10000 steps, the ant builds a highway: every 104
+        // steps, it moves two cells diagonally and leaves 12 more cells.
+        let mut world = still_world(160, 160);
+        world
+            .add_ant(Turmite::langtons_ant(), (80, 80), Direction::North)
+            .unwrap();
+        for _ in 0..11000 {
+            world.next_tick();
+        }
+        let (start, population) = (world.ants()[0].position(), world.population());
+        for _ in 0..104 {
+            world.next_tick();
+        }
+        let end = world.ants()[0].position();
+        assert_eq!(
+            ((end.row - start.row).abs(), (end.col - start.col).abs()),
+            (2, 2)
+        );
+        assert_eq!(world.population(), population + 12);
+    }
+
+    #[test]
+    fn many_colors_test() {
+        let mut world = still_world(8, 8);
+        let turmite: Turmite = "LLRR".parse().unwrap();
+        assert!(world
+            .add_ant(turmite.clone(), (0, 0), Direction::North)
+            .is_err());
+
+        world
+            .load_rule_table(
+                "@RULE Still\n@TABLE\nn_states:4\nneighborhood:Moore\nsymmetries:none\n",
+            )
+            .unwrap();
+        world.add_ant(turmite, (4, 4), Direction::East).unwrap();
+        world
+            .add_ant(Turmite::langtons_ant(), (0, 0), Direction::South)
+            .unwrap();
+        for _ in 0..3 {
+            world.next_tick();
+        }
+        // On color 0, the first ant turns left and the other one right.
+        assert_eq!(world.state((4, 4)), 1);
+        assert_eq!(world.ants()[0].position(), Coord::new(4, 3));
+        assert_eq!(world.ants()[1].position(), Coord::new(7, 0));
+        assert_eq!(world.population(), 6);
+    }
+
+    #[test]
+    fn plane_edges_test() {
+        let mut world = still_world(4, 4);
+        world.set_topology(Topology::Plane);
+        world
+            .add_ant(Turmite::langtons_ant(), (1, 3), Direction::North)
+            .unwrap();
+        world
+            .add_ant(Turmite::langtons_ant(), (2, 1), Direction::North)
+            .unwrap();
+        world.next_tick();
+        assert_eq!(world.ants().len(), 1);
+        assert_eq!(world.ants()[0].position(), Coord::new(2, 2));
+        world.clear_ants();
+        assert!(world.ants().is_empty());
+    }
+}
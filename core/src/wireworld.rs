@@ -0,0 +1,133 @@
+//! WireWorld, the four-state automaton made to simulate circuits: electrons,
+//! a head followed by a tail, run along conductors.
+//!
+//! States are numbered as in Golly, so patterns load in either program.
+
+/// The states of a WireWorld cell.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Wire {
+    Empty = 0,
+    Head = 1,
+    Tail = 2,
+    Conductor = 3,
+}
+
+impl From<Wire> for u8 {
+    fn from(wire: Wire) -> u8 {
+        wire as u8
+    }
+}
+
+pub(crate) const STATES: usize = 4;
+
+const HEAD: u8 = Wire::Head as u8;
+const TAIL: u8 = Wire::Tail as u8;
+const CONDUCTOR: u8 = Wire::Conductor as u8;
+
+/// Heads become tails, tails become conductors, and conductors become heads
+/// next to one or two heads.
+pub(crate) fn next_state(center: u8, neighbors: &[u8]) -> u8 {
+    match center {
+        HEAD => TAIL,
+        TAIL => CONDUCTOR,
+        CONDUCTOR => match neighbors.iter().filter(|&&n| n == HEAD).count() {
+            1 | 2 => HEAD,
+            _ => CONDUCTOR,
+        },
+        state => state,
+    }
+}
+
+pub(crate) fn color(state: u8) -> Option<u32> {
+    match state {
+        HEAD => Some(0x0080ff),
+        TAIL => Some(0xffffff),
+        CONDUCTOR => Some(0xff8000),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Automaton, Rect, World};
+
+    /// A loop of ten conductors around which an electron runs.
+    const CLOCK: &str = "x = 6, y = 3, rule = WireWorld
+.BA2C$C4.C$.4C!
+";
+
+    #[test]
+    fn next_state_test() {
+        assert_eq!(next_state(HEAD, &[0; 8]), TAIL);
+        assert_eq!(next_state(TAIL, &[HEAD; 8]), CONDUCTOR);
+        assert_eq!(next_state(CONDUCTOR, &[HEAD, 0, 0, 0, 0, 0, 0, 0]), HEAD);
+        assert_eq!(next_state(CONDUCTOR, &[HEAD, HEAD, 0, 0, 0, 0, 0, 0]), HEAD);
+        assert_eq!(
+            next_state(CONDUCTOR, &[HEAD, HEAD, HEAD, 0, 0, 0, 0, 0]),
+            CONDUCTOR
+        );
+        assert_eq!(next_state(CONDUCTOR, &[TAIL; 8]), CONDUCTOR);
+        assert_eq!(next_state(0, &[HEAD; 8]), 0);
+    }
+
+    #[test]
+    fn rle_round_trip_test() {
+        let mut world = World::new(12, 9);
+        world.set_automaton(Some(Automaton::WireWorld));
+        world.load_rle_str(CLOCK).unwrap();
+        assert_eq!(world.state((3, 4)), u8::from(Wire::Tail));
+        assert_eq!(world.state((3, 5)), u8::from(Wire::Head));
+        assert_eq!(world.population(), 10);
+
+        let exported = world.export_rle();
+        assert_eq!(exported, format!("#R 0 0\n{}", CLOCK));
+
+        let mut reloaded = World::new(12, 9);
+        reloaded.set_automaton(Some(Automaton::WireWorld));
+        reloaded.load_rle_lenient(&exported).unwrap();
+        assert!(reloaded.states().eq(world.states()));
+    }
+
+    #[test]
+    fn clock_test() {
+        let mut world = World::new(12, 9);
+        world.set_automaton(Some(Automaton::WireWorld));
+        world.load_rle_str(CLOCK).unwrap();
+        let start: Vec<u8> = world.states().collect();
+        for _ in 0..10 {
+            world.next_tick();
+            let heads = world.states().filter(|&s| s == HEAD).count();
+            assert_eq!(heads, 1);
+        }
+        assert!(world.states().eq(start));
+    }
+
+    #[test]
+    fn drawing_conductors_test() {
+        let mut world = World::new(8, 8);
+        world.set_automaton(Some(Automaton::WireWorld));
+        world.draw_line((1, 1), (1, 6), Wire::Conductor);
+        world.draw_rect(
+            &Rect {
+                x: 0,
+                y: 3,
+                width: 8,
+                height: 3,
+            },
+            Wire::Conductor,
+            crate::Fill::Outline,
+        );
+        world.set_state((1, 1), Wire::Head.into());
+        assert_eq!(world.population(), 6 + 18);
+
+        // The inside of the rectangle, then the whole background.
+        world.flood_fill((4, 4), Wire::Tail, crate::Connectivity::Four);
+        assert_eq!(world.state((4, 6)), TAIL);
+        assert_eq!(world.state((0, 0)), 0);
+        world.flood_fill((0, 0), Wire::Conductor, crate::Connectivity::Four);
+        assert_eq!(world.states().filter(|&s| s == 0).count(), 0);
+        assert_eq!(world.state((1, 1)), HEAD);
+    }
+}
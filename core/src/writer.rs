@@ -0,0 +1,251 @@
+//! RLE encoding.
+//!
+//! `RleWriter` takes runs in any granularity, down to single cells, and
+//! writes the canonical compact form: consecutive runs of the same state are
+//! merged, counts of 1 are omitted, dead cells at the end of a row and blank
+//! rows at the end of the pattern are dropped, and lines are wrapped at
+//! `LINE_WIDTH` columns without splitting a run.
+
+use std::fmt::{self, Write};
+
+use super::parser::{multi_state_name, RleTag};
+
+/// Longest line written, as recommended by the RLE format description.
+pub const LINE_WIDTH: usize = 70;
+
+pub struct RleWriter<W> {
+    out: W,
+    column: usize,
+    // `.` rather than `b` for dead cells, in multi-state patterns.
+    dead_tag: char,
+    // Runs not written yet, in this order: they can still grow, or turn out
+    // to be trailing and be dropped.
+    live: usize,
+    live_tag: RleTag,
+    lines: usize,
+    dead: usize,
+}
+
+impl<W: Write> RleWriter<W> {
+    pub fn new(out: W) -> RleWriter<W> {
+        RleWriter {
+            out,
+            column: 0,
+            dead_tag: 'b',
+            live: 0,
+            live_tag: RleTag::Alive,
+            lines: 0,
+            dead: 0,
+        }
+    }
+
+    /// A writer of multi-state patterns, which take `RleTag::State` cells.
+    pub fn multi_state(out: W) -> RleWriter<W> {
+        RleWriter {
+            dead_tag: '.',
+            ..RleWriter::new(out)
+        }
+    }
+
+    pub fn push(&mut self, count: usize, tag: RleTag) -> fmt::Result {
+        if count == 0 {
+            return Ok(());
+        }
+        match tag {
+            RleTag::Dead => self.dead += count,
+            RleTag::NextLine => {
+                self.dead = 0;
+                self.lines += count;
+            }
+            _ if self.lines == 0 && self.dead == 0 && (self.live == 0 || self.live_tag == tag) => {
+                self.live += count;
+                self.live_tag = tag;
+            }
+            _ => {
+                self.flush()?;
+                self.live = count;
+                self.live_tag = tag;
+            }
+        }
+        Ok(())
+    }
+
+    /// Ends the pattern with `!` and a newline, and returns the output.
+    pub fn finish(mut self) -> Result<W, fmt::Error> {
+        let (live, tag) = (self.live, self.tag(self.live_tag));
+        self.write_run(live, &tag)?;
+        self.write_token("!")?;
+        self.out.write_char('\n')?;
+        Ok(self.out)
+    }
+
+    fn flush(&mut self) -> fmt::Result {
+        let (live, lines, dead) = (self.live, self.lines, self.dead);
+        let (live_tag, dead_tag) = (self.tag(self.live_tag), self.tag(RleTag::Dead));
+        self.write_run(live, &live_tag)?;
+        self.write_run(lines, "$")?;
+        self.write_run(dead, &dead_tag)?;
+        self.live = 0;
+        self.lines = 0;
+        self.dead = 0;
+        Ok(())
+    }
+
+    fn tag(&self, tag: RleTag) -> String {
+        match tag {
+            RleTag::NextLine => "$".to_string(),
+            RleTag::Dead => self.dead_tag.to_string(),
+            RleTag::Alive => "o".to_string(),
+            RleTag::State(state) => multi_state_name(state),
+        }
+    }
+
+    fn write_run(&mut self, count: usize, tag: &str) -> fmt::Result {
+        match count {
+            0 => Ok(()),
+            1 => self.write_token(tag),
+            n => self.write_token(&format!("{}{}", n, tag)),
+        }
+    }
+
+    fn write_token(&mut self, token: &str) -> fmt::Result {
+        if self.column + token.len() > LINE_WIDTH {
+            self.out.write_char('\n')?;
+            self.column = 0;
+        }
+        self.column += token.len();
+        self.out.write_str(token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_rle;
+    use crate::{Cell, World};
+
+    fn encode(runs: &[(usize, RleTag)]) -> String {
+        let mut writer = RleWriter::new(String::new());
+        for (count, tag) in runs {
+            writer.push(*count, *tag).unwrap();
+        }
+        writer.finish().unwrap()
+    }
+
+    #[test]
+    fn compact_form_test() {
+        use RleTag::*;
+
+        // Glider, fed cell by cell with trailing dead cells and rows.
+        let glider = encode(&[
+            (1, Dead),
+            (1, Alive),
+            (1, Dead),
+            (1, NextLine),
+            (1, Dead),
+            (1, Dead),
+            (1, Alive),
+            (1, NextLine),
+            (1, Alive),
+            (1, Alive),
+            (1, Alive),
+            (1, NextLine),
+            (3, Dead),
+            (1, NextLine),
+        ]);
+        assert_eq!(glider, "bo$2bo$3o!\n");
+
+        let blank_rows = encode(&[
+            (2, Alive),
+            (4, Dead),
+            (1, NextLine),
+            (1, NextLine),
+            (1, Alive),
+        ]);
+        assert_eq!(blank_rows, "2o2$o!\n");
+
+        assert_eq!(encode(&[(0, Alive), (3, NextLine)]), "!\n");
+    }
+
+    #[test]
+    fn multi_state_test() {
+        use RleTag::*;
+
+        let mut writer = RleWriter::multi_state(String::new());
+        let runs = [
+            (2, State(3)),
+            (1, State(3)),
+            (1, State(1)),
+            (2, Dead),
+            (1, State(30)),
+            (1, Dead),
+            (1, NextLine),
+            (1, State(2)),
+            (1, Dead),
+        ];
+        for (count, tag) in runs.iter() {
+            writer.push(*count, *tag).unwrap();
+        }
+        assert_eq!(writer.finish().unwrap(), "3CA2.pF$B!\n");
+    }
+
+    #[test]
+    fn line_wrapping_test() {
+        use RleTag::*;
+
+        let runs: Vec<_> = (0..40)
+            .flat_map(|_| vec![(10, Alive), (10, Dead)])
+            .collect();
+        let rle = encode(&runs);
+        assert!(rle.lines().all(|line| line.len() <= LINE_WIDTH));
+        assert!(rle.lines().all(|line| !line.ends_with(char::is_numeric)));
+        assert_eq!(rle.lines().next().unwrap().len(), 69);
+        assert_eq!(rle.replace('\n', ""), "10o10b".repeat(39) + "10o!");
+    }
+
+    #[test]
+    fn export_rle_test() {
+        let mut world = World::new(16, 16);
+        world.load_rle_str("x = 3, y = 3\nbo$2bo$3o!").unwrap();
+        assert_eq!(
+            world.export_rle(),
+            "#R 0 0\nx = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n"
+        );
+
+        let mut world = World::new(16, 16);
+        world.set_cell((2, 2), Cell::Alive);
+        world.set_cell((5, 2), Cell::Alive);
+        world.set_cell((5, 4), Cell::Alive);
+        assert_eq!(
+            world.export_rle(),
+            "#R -5 -4\nx = 3, y = 4, rule = B3/S23\no3$obo!\n"
+        );
+    }
+
+    #[test]
+    fn patterns_round_trip_test() {
+        use std::fs;
+
+        let patterns = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../patterns");
+        for entry in fs::read_dir(patterns).unwrap() {
+            let path = entry.unwrap().path();
+            let content = fs::read_to_string(&path).unwrap();
+            let (_, rle) = parse_rle(content.as_str().into()).unwrap();
+            let (width, height) = (rle.size.0 as i32 + 8, rle.size.1 as i32 + 8);
+
+            let mut world = World::new(width, height);
+            world.load_rle(rle).unwrap();
+            let exported = world.export_rle();
+            assert!(
+                exported.lines().all(|line| line.len() <= LINE_WIDTH),
+                "{:?}",
+                path
+            );
+
+            let mut reloaded = World::new(width, height);
+            reloaded.load_rle_str(&exported).unwrap();
+            assert_eq!(reloaded.cells(), world.cells(), "{:?}", path);
+            assert_eq!(reloaded.export_rle(), exported, "{:?}", path);
+        }
+    }
+}
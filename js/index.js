@@ -4,6 +4,7 @@ const App = require('./app')
 
 gameOfLife('build/game_of_life_bg.wasm').then((wasm) => {
   World = gameOfLife.World
+  Renderer = gameOfLife.Renderer
 
   ReactDOM.render(
     <App World={World} wasm={wasm} />,
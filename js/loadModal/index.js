@@ -33,6 +33,11 @@ class LoadModal extends React.Component {
   constructor(props) {
     super(props);
     this.textareaRef = React.createRef();
+    this.patterns = JSON.parse(gameOfLife.find_patterns("", "", "", 0));
+  }
+
+  handlePatternChange(event) {
+    this.textareaRef.current.value = gameOfLife.pattern_rle(event.target.value);
   }
 
   handleSubmit() {
@@ -51,6 +56,22 @@ class LoadModal extends React.Component {
           onClose={this.props.onClose}
         >
           <div style={getModalStyle()} className={classes.paper}>
+            <select
+              defaultValue=""
+              style={{ display: "block", marginBottom: "15px" }}
+              onChange={this.handlePatternChange.bind(this)}
+            >
+              <option value="" disabled>
+                Pick a pattern from the library, or paste an RLE below
+              </option>
+              {this.patterns.map(pattern => (
+                <option key={pattern.id} value={pattern.id}>
+                  {pattern.name}
+                  {pattern.author ? ` by ${pattern.author}` : ""} ({pattern.kind}
+                  {pattern.period ? `, p${pattern.period}` : ""})
+                </option>
+              ))}
+            </select>
             <textarea
               ref={this.textareaRef}
               style={{ resize: "none", width: "600px", height: "300px" }}
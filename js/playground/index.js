@@ -1,102 +1,42 @@
 const React = require('react')
 
-const DEAD_COLOR = '#FFFFFF'
-
-const getCells = (wasm, world) => {
-  const cellsPtr = world.cells()
-  const cells = new Uint32Array(
-    wasm.memory.buffer,
-    cellsPtr,
-    world.width() * world.height()
-  )
-  return cells
-}
-
-const getChangedCells = (wasm, world) => {
-  const changedCellsPtr = world.changed_cells()
-  const changedCellsLen = world.changed_cells_len()
-  const cells = new Uint32Array(
-    wasm.memory.buffer,
-    changedCellsPtr,
-    changedCellsLen
-  )
-  return cells
-}
-
-const fromIndex = (world, idx) => {
-  const col = idx % world.width()
-  const row = Math.floor(idx / world.width())
-  return [row, col]
-}
-
-const drawCells = (ctx, wasm, world, cellSize, cellColor) => {
-  const cells = getCells(wasm, world)
-
-  ctx.beginPath()
-
-  for (let i = 0; i < cells.length; i++) {
-    const [row, col] = fromIndex(world, i)
-    const cell = cells[i]
-
-    ctx.fillStyle = cell === 0 ? DEAD_COLOR : cellColor
-    ctx.fillRect(
-      col * cellSize + 1,
-      row * cellSize + 1,
-      cellSize - 1,
-      cellSize - 1
-    )
-  }
-
-  world.reset_changed_cells()
-  ctx.stroke()
-}
-
-const drawChangedCells = (ctx, wasm, world, cellSize, cellColor) => {
-  const cells = getCells(wasm, world)
-  const cellsIndexes = getChangedCells(wasm, world)
-
-  ctx.beginPath()
-
-  for (let i = 0; i < cellsIndexes.length; i++) {
-    const index = cellsIndexes[i]
-    const [row, col] = fromIndex(world, index)
-    const cell = cells[index]
-
-    ctx.fillStyle = cell === 0 ? DEAD_COLOR : cellColor
-    ctx.fillRect(
-      col * cellSize + 1,
-      row * cellSize + 1,
-      cellSize - 1,
-      cellSize - 1
-    )
-  }
-
-  world.reset_changed_cells()
-  ctx.stroke()
-}
+const hexToInt = hex => parseInt(hex.slice(1), 16)
 
 class Playground extends React.Component {
   constructor (props) {
     super(props)
     this.canvasRef = React.createRef()
+    this.renderer = Renderer.new(
+      props.world.width() * props.cellSize,
+      props.world.height() * props.cellSize,
+      props.cellSize
+    )
+    this.renderer.set_alive_color(hexToInt(props.cellColor))
   }
 
   componentDidMount (prevProps) {
     this.drawCanvas()
   }
 
+  componentWillUnmount () {
+    this.renderer.free()
+  }
+
   componentDidUpdate (prevProps, prevState, snapshot) {
-    let force = false
     if (prevProps && prevProps.cellColor !== this.props.cellColor) {
-      force = true
+      this.renderer.set_alive_color(hexToInt(this.props.cellColor))
+    }
+    if (prevProps && prevProps.world !== this.props.world) {
+      this.renderer.invalidate()
     }
-    this.drawCanvas(force)
+    this.drawCanvas()
   }
 
-  drawCanvas (force = false) {
-    const { world, wasm } = this.props
-    const ctx = this.canvasRef.current.getContext('2d')
-    this.canvasRef.current.style = `
+  drawCanvas () {
+    const { world, wasm, cellSize } = this.props
+    const canvas = this.canvasRef.current
+    const ctx = canvas.getContext('2d')
+    canvas.style = `
 image-rendering: optimizeSpeed;
 image-rendering: -moz-crisp-edges;
 image-rendering: -webkit-optimize-contrast;
@@ -106,11 +46,22 @@ image-rendering: crisp-edges;
 image-rendering: pixelated;
 -ms-interpolation-mode: nearest-neighbor;
 `
-    if (force) {
-      drawCells(ctx, wasm, world, this.props.cellSize, this.props.cellColor)
-    } else {
-      drawChangedCells(ctx, wasm, world, this.props.cellSize, this.props.cellColor)
+    if (
+      this.renderer.width() !== canvas.width ||
+      this.renderer.height() !== canvas.height
+    ) {
+      this.renderer.resize(canvas.width, canvas.height)
     }
+    this.renderer.set_cell_size(cellSize)
+    this.renderer.render(world)
+    world.reset_changed_cells()
+
+    const pixels = new Uint8ClampedArray(
+      wasm.memory.buffer,
+      this.renderer.pixels(),
+      this.renderer.pixels_len()
+    )
+    ctx.putImageData(new ImageData(pixels, canvas.width, canvas.height), 0, 0)
   }
 
   handleClick (event) {
@@ -123,8 +74,11 @@ image-rendering: pixelated;
     const canvasLeft = (event.clientX - boundingRect.left) * scaleX
     const canvasTop = (event.clientY - boundingRect.top) * scaleY
 
-    const row = Math.floor(canvasTop / this.props.cellSize)
-    const col = Math.floor(canvasLeft / this.props.cellSize)
+    const [row, col] = this.renderer.cell_at(
+      this.props.world,
+      Math.floor(canvasLeft),
+      Math.floor(canvasTop)
+    )
     this.props.toggleCell(row, col)
   }
 
@@ -1,38 +1,22 @@
-use console_error_panic_hook;
-
-use image;
-use image::GenericImageView;
+//! JavaScript bindings for `game-of-life-core`.
+//!
+//! Everything here is a thin layer over the engine: it converts arguments
+//! from the shapes JS can pass, logs errors to the console and exposes raw
+//! pointers into wasm memory for the buffers JS reads directly.
+
+use game_of_life_core as engine;
+use game_of_life_core::library;
 use wasm_bindgen::prelude::*;
 
-use std::fmt::Write;
-
-mod number_hashset;
-mod parser;
-
-struct Rect<N> {
-    x: N,
-    y: N,
-    width: N,
-    height: N,
-}
-
 #[wasm_bindgen]
 extern "C" {
     #[wasm_bindgen(js_namespace=console)]
     fn log(s: &str);
 }
 
-#[wasm_bindgen]
-pub struct World {
-    width: i32,
-    height: i32,
-    cells: Vec<Cell>,
-    cache: Vec<Cell>,
-    generations: u32,
-    changed_cells: Vec<i32>,
-}
+/// Age reported for cells that have been dead since age tracking started.
+const NEVER_ALIVE: u32 = u32::MAX;
 
-#[repr(C)]
 #[wasm_bindgen]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Cell {
@@ -40,378 +24,621 @@ pub enum Cell {
     Alive = 1,
 }
 
-use image::Pixel;
-impl std::convert::From<image::Rgba<u8>> for Cell {
-    fn from(a: image::Rgba<u8>) -> Self {
-        let l = a.to_luma();
-        if l.data[0] < 255 / 2 {
-            Cell::Alive
-        } else {
-            Cell::Dead
+impl From<Cell> for engine::Cell {
+    fn from(cell: Cell) -> Self {
+        match cell {
+            Cell::Dead => engine::Cell::Dead,
+            Cell::Alive => engine::Cell::Alive,
         }
     }
 }
 
-fn flatten<T>(o: Option<Option<T>>) -> Option<T> {
-    match o {
-        Some(Some(t)) => Some(t),
-        _ => None,
+fn fill(filled: bool) -> engine::Fill {
+    if filled {
+        engine::Fill::Solid
+    } else {
+        engine::Fill::Outline
     }
 }
 
-pub struct FirstN<'a, I> {
-    inner: &'a mut I,
-    n: usize,
-    count: usize,
+#[wasm_bindgen]
+pub struct World {
+    inner: engine::World,
+    ages: Vec<u32>,
 }
 
-impl<'a, I: 'a + Iterator> Iterator for FirstN<'a, I> {
-    type Item = <I as Iterator>::Item;
+#[wasm_bindgen]
+impl World {
+    pub fn new(width: i32, height: i32) -> World {
+        console_error_panic_hook::set_once();
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.count >= self.n {
-            return None;
+        World {
+            inner: engine::World::new(width, height),
+            ages: Vec::new(),
         }
-        self.count += 1;
-        self.inner.next()
     }
-}
 
-fn first_n<I>(i: &mut I, n: usize) -> FirstN<I> {
-    FirstN {
-        inner: i,
-        n,
-        count: 0,
+    pub fn from_image(data: Vec<u8>) -> World {
+        console_error_panic_hook::set_once();
+
+        World {
+            inner: engine::World::from_image(&data).expect("Invalid image data"),
+            ages: Vec::new(),
+        }
     }
-}
 
-#[wasm_bindgen]
-impl World {
-    pub fn load_string(&mut self, pattern: String) {
-        let res = parser::parse_rle(pattern.as_str().into());
-        match res {
-            Ok((_, pat)) => {
-                self.load_rle(pat);
-            }
-            Err(_) => {
-                log("Failed to parse rle string");
+    /// Rebuilds a world saved with `save_snapshot`, or returns nothing and
+    /// logs why if `bytes` are not a valid snapshot.
+    pub fn from_snapshot(bytes: &[u8]) -> Option<World> {
+        console_error_panic_hook::set_once();
+
+        match engine::World::load_snapshot(bytes) {
+            Ok(inner) => Some(World {
+                inner,
+                ages: Vec::new(),
+            }),
+            Err(e) => {
+                log(&e.to_string());
+                None
             }
         }
     }
 
-    pub fn width(&self) -> i32 {
-        self.width
+    /// Replaces this world with the one saved in `bytes`, keeping it intact
+    /// if they are not a valid snapshot.
+    pub fn load_snapshot(&mut self, bytes: &[u8]) {
+        match engine::World::load_snapshot(bytes) {
+            Ok(world) => self.inner = world,
+            Err(e) => log(&e.to_string()),
+        }
     }
 
-    pub fn height(&self) -> i32 {
-        self.height
+    /// Cells, dimensions, generation count, rule or automaton, topology and
+    /// ants, in the compact binary format described in
+    /// `game_of_life_core::snapshot`.
+    pub fn save_snapshot(&self) -> Vec<u8> {
+        self.inner.save_snapshot()
     }
 
-    fn pattern_boundaries(&self) -> Option<Rect<usize>> {
-        let first_line_idx = self.cells.iter().position(|c| *c == Cell::Alive);
-        if let None = first_line_idx {
-            return None;
+    /// Loads an RLE pattern, tolerating the usual defects of files found in
+    /// the wild; they are reported on the console.
+    pub fn load_string(&mut self, pattern: String) {
+        match self.inner.load_rle_lenient(&pattern) {
+            Ok(warnings) => warnings.iter().for_each(|w| log(&w.to_string())),
+            Err(e) => log(&e.to_string()),
         }
-        let (first_line, _) = self.from_index(first_line_idx.unwrap() as i32);
-        let first_line = first_line as usize;
+    }
 
-        let last_line_idx = self.cells.iter().rposition(|c| *c == Cell::Alive);
-        if let None = last_line_idx {
-            return None;
+    /// Places the library pattern `id` (see `find_patterns`).
+    pub fn load_pattern(&mut self, id: &str) {
+        if let Err(e) = self.inner.load_pattern(id) {
+            log(&e.to_string());
         }
-        let (last_line, _) = self.from_index(last_line_idx.unwrap() as i32);
-        let last_line = last_line as usize;
-
-        let mut first_column = self.width as usize;
-        let mut last_column = 0;
-
-        let mut cells = self.cells.iter();
-        for _ in 0..self.height {
-            let mut row = first_n(&mut cells, self.width as usize).enumerate();
-            let first_col_alive = row.find(|(_, c)| **c == Cell::Alive);
-            let last_col_alive = row
-                .filter(|(_, c)| **c == Cell::Alive)
-                .last()
-                .or(first_col_alive);
-            if let Some((pos, _)) = first_col_alive {
-                first_column = ::std::cmp::min(first_column, pos);
-            }
-            if let Some((pos, _)) = last_col_alive {
-                last_column = ::std::cmp::max(last_column, pos);
-            }
+    }
+
+    /// Places the object named by the apgcode `code`, e.g. `xq4_153`.
+    pub fn load_apgcode(&mut self, code: &str) {
+        if let Err(e) = self.inner.load_apgcode(code) {
+            log(&e.to_string());
         }
-        Some(Rect {
-            x: first_column,
-            y: first_line,
-            width: last_column - first_column + 1,
-            height: last_line - first_line + 1,
+    }
+
+    /// The apgcode of the object in the given rectangle, or an empty string
+    /// if it isn't a still life, oscillator or spaceship.
+    pub fn apgcode(&self, row: i32, col: i32, width: i32, height: i32) -> String {
+        let rect = engine::Rect {
+            x: col,
+            y: row,
+            width,
+            height,
+        };
+        self.inner.apgcode(&rect).unwrap_or_else(|e| {
+            log(&e.to_string());
+            String::new()
         })
     }
 
-    pub fn export_rle(&self) -> String {
-        let pattern_boundaries = self.pattern_boundaries();
+    /// A parent of the pattern, as RLE, made of cells at most `margin` cells
+    /// away from its bounding box. Trying at most `max_nodes` cell values, or
+    /// when there is none, returns nothing and logs why.
+    pub fn find_predecessor(&self, margin: u32, max_nodes: u32) -> Option<String> {
+        match self.inner.find_predecessor(margin, u64::from(max_nodes)) {
+            Ok(engine::Predecessor::Found(rle)) => Some(rle),
+            Ok(outcome) => {
+                log(&outcome.to_string());
+                None
+            }
+            Err(e) => {
+                log(&e.to_string());
+                None
+            }
+        }
+    }
 
-        if pattern_boundaries.is_none() {
-            return "".to_string();
+    /// Looks for oscillators or spaceships of `period` moving by `(rows,
+    /// cols)` in a `width` by `height` box with the given drawing symmetry,
+    /// trying at most `max_nodes` cell values. `on_event` is called with
+    /// `"found"` and each pattern as RLE, and with `"partial"` and the cells
+    /// decided so far every `report_every` cell values, never when 0; the
+    /// search stops when it returns `false`.
+    ///
+    /// Returns how the search ended: `"exhausted"`, `"stopped"` or
+    /// `"gave up"`, or an empty string after logging why it couldn't run.
+    #[allow(clippy::too_many_arguments)]
+    pub fn search_ships(
+        &self,
+        period: u32,
+        rows: i32,
+        cols: i32,
+        width: u32,
+        height: u32,
+        symmetry: &str,
+        max_nodes: u32,
+        report_every: u32,
+        on_event: &js_sys::Function,
+    ) -> String {
+        let mut search = engine::ShipSearch::new(period, (rows, cols), width, height);
+        search.max_nodes = u64::from(max_nodes);
+        search.report_every = u64::from(report_every);
+        match symmetry.parse() {
+            Ok(symmetry) => search.symmetry = symmetry,
+            Err(e) => {
+                log(&e.to_string());
+                return String::new();
+            }
         }
-        let bounds = pattern_boundaries.unwrap();
-
-        let mut buff = String::new();
-
-        let center_col = self.width / 2;
-        let center_row = self.height / 2;
-        write!(
-            &mut buff,
-            "#R {} {}\nx = {}, y = {}\n",
-            bounds.x as i32 - center_col,
-            bounds.y as i32 - center_row,
-            bounds.width,
-            bounds.height
-        )
-        .ok();
-
-        self.write_pattern(bounds, &mut buff);
-
-        buff
-    }
-
-    fn write_pattern<W: std::fmt::Write>(&self, bounds: Rect<usize>, mut w: W) -> () {
-        let mut cells = self.cells.iter().skip(bounds.y * self.width as usize);
-        for _ in bounds.y..=(bounds.y + bounds.height) {
-            let mut row = first_n(&mut cells, self.width as usize)
-                .skip(bounds.x)
-                .peekable();
-            while let Some(cell) = row.next() {
-                let mut n = 1;
-                while let Some(&c) = row.peek() {
-                    if c == cell {
-                        n += 1;
-                        row.next();
-                    } else {
-                        break;
-                    }
-                }
-                let c = match cell {
-                    Cell::Alive => 'o',
-                    Cell::Dead => 'b',
-                };
-                if *cell == Cell::Alive || row.peek().is_some() {
-                    write!(w, "{}{}", n, c).ok();
+        let result = self.inner.search_ships(&search, |event| {
+            let (kind, rle) = match event {
+                engine::SearchEvent::Partial(rle) => ("partial", rle),
+                engine::SearchEvent::Found(rle) => ("found", rle),
+            };
+            match on_event.call2(&JsValue::NULL, &kind.into(), &rle.into()) {
+                Ok(more) => more.as_bool() != Some(false),
+                Err(e) => {
+                    log(&format!("{:?}", e));
+                    false
                 }
             }
-            write!(w, "{}", '$').ok();
+        });
+        match result {
+            Ok(engine::SearchEnd::Exhausted) => "exhausted".to_string(),
+            Ok(engine::SearchEnd::Stopped) => "stopped".to_string(),
+            Ok(engine::SearchEnd::GaveUp) => "gave up".to_string(),
+            Err(e) => {
+                log(&e.to_string());
+                String::new()
+            }
         }
-        write!(w, "{}", '!').ok();
     }
 
-    pub fn resize(&mut self, width: i32, height: i32) {
-        let mut new_world = World::new(width, height);
-        let copy_end_width = ::std::cmp::min(width, self.width);
-        let copy_end_height = ::std::cmp::min(height, self.height);
-        for row in 0..copy_end_height {
-            for col in 0..copy_end_width {
-                new_world.set_cell(row, col, self.get(row, col));
-            }
+    /// Sets the rule, in `B3/S23` or `23/3` notation.
+    pub fn set_rule(&mut self, rule: &str) {
+        match rule.parse() {
+            Ok(rule) => self.inner.set_rule(rule),
+            Err(e) => log(&e.to_string()),
         }
+    }
 
-        ::std::mem::swap(self, &mut new_world);
+    pub fn rule(&self) -> String {
+        self.inner.rule().to_string()
     }
 
-    fn load_rle(&mut self, rle: parser::Rle) {
-        let coords = rle
-            .comments
-            .iter()
-            .map(|c| match c {
-                parser::RleComment::Coordinates(x, y) => Some((*x, *y)),
-                _ => None,
-            })
-            .find(Option::is_some);
-        let (x, y) = flatten(coords).unwrap_or((0, 0));
-
-        let origin_x = self.width / 2;
-        let origin_y = self.height / 2;
-
-        let top_left_x = origin_x + x - (rle.size.0 / 2) as i32;
-        let top_left_y = origin_y + y - (rle.size.1 / 2) as i32;
-
-        let mut i = top_left_x;
-        let mut j = top_left_y;
-        rle.content.iter().for_each(|seq| match seq {
-            parser::RleTagSequence(count, parser::RleTag::NextLine) => {
-                (0..*count).for_each(|_| {
-                    j += 1;
-                });
-                i = top_left_x;
-            }
-            parser::RleTagSequence(count, state) => (0..*count).for_each(|_| {
-                let cell = match state {
-                    parser::RleTag::Dead => Cell::Dead,
-                    parser::RleTag::Alive => Cell::Alive,
-                    _ => unreachable!(),
-                };
-                self.set_cell(j, i, cell);
-                i += 1;
-            }),
-        })
+    /// Runs the table of a Golly `.rule` file instead of the rule.
+    pub fn load_rule_table(&mut self, table: &str) {
+        if let Err(e) = self.inner.load_rule_table(table) {
+            log(&e.to_string());
+        }
     }
 
-    #[inline(always)]
-    fn get_index(&self, mut row: i32, mut col: i32) -> i32 {
-        if row < 0 {
-            row += self.height;
-        };
-        if col < 0 {
-            col += self.width;
-        };
-        let col = col % self.width;
-        let row = row % self.height;
-        (row * self.width + col)
+    /// Runs a built-in automaton instead of the rule: `WireWorld`, whose
+    /// states are 1 for heads, 2 for tails and 3 for conductors, the block
+    /// rules `BBM` and `Critters`, or a block rule in `MS,D` notation.
+    pub fn set_automaton(&mut self, name: &str) {
+        match name.parse() {
+            Ok(automaton) => self.inner.set_automaton(Some(automaton)),
+            Err(e) => log(&e.to_string()),
+        }
+    }
+
+    /// Goes back to the two-state rule.
+    pub fn clear_automaton(&mut self) {
+        self.inner.set_automaton(None);
+    }
+
+    /// Sets the topology: `torus` or `plane`.
+    pub fn set_topology(&mut self, topology: &str) {
+        match topology.parse() {
+            Ok(topology) => self.inner.set_topology(topology),
+            Err(e) => log(&e.to_string()),
+        }
     }
 
-    #[inline(always)]
-    fn get(&self, row: i32, col: i32) -> Cell {
-        self.cells[self.get_index(row, col) as usize]
+    pub fn population(&self) -> u32 {
+        self.inner.population()
+    }
+
+    pub fn width(&self) -> i32 {
+        self.inner.width()
+    }
+
+    pub fn height(&self) -> i32 {
+        self.inner.height()
+    }
+
+    pub fn generations(&self) -> u32 {
+        self.inner.generations()
+    }
+
+    pub fn export_rle(&self) -> String {
+        self.inner.export_rle()
+    }
+
+    pub fn resize(&mut self, width: i32, height: i32) {
+        self.inner.resize(width, height);
     }
 
     pub fn set_cell(&mut self, row: i32, col: i32, t: Cell) {
-        let idx = self.get_index(row, col);
-        self.changed_cells.push(idx);
-        let idx = idx as usize;
-        self.cells[idx] = t;
+        self.inner.set_cell((row, col), t.into());
+    }
+
+    pub fn state(&self, row: i32, col: i32) -> u8 {
+        self.inner.state((row, col))
     }
 
-    fn set(&mut self, row: i32, col: i32, t: Cell) {
-        let idx = self.get_index(row, col) as usize;
-        self.cache[idx] = t;
+    pub fn set_state(&mut self, row: i32, col: i32, state: u8) {
+        self.inner.set_state((row, col), state);
     }
 
     pub fn clear(&mut self) {
-        self.cells.iter_mut().for_each(|cell| *cell = Cell::Dead);
-        self.reset_changed_cells();
-        self.changed_cells = (0..self.cells.len() as i32).collect();
+        self.inner.clear();
     }
 
     pub fn toggle(&mut self, row: i32, col: i32) {
-        let idx = self.get_index(row, col);
-        self.changed_cells.push(idx);
-        let idx = idx as usize;
-        self.cells[idx] = match self.cells[idx] {
-            Cell::Dead => Cell::Alive,
-            Cell::Alive => Cell::Dead,
+        self.inner.toggle((row, col));
+    }
+
+    /// Mirrors every edit according to `symmetry`: `none`, `horizontal`,
+    /// `vertical`, `both`, `diagonal`, `90` or `180`. The center is given in
+    /// half cells, `(2 * row, 2 * col)` being the center of a cell.
+    pub fn set_draw_symmetry(&mut self, symmetry: &str, center_row: i32, center_col: i32) {
+        match symmetry.parse() {
+            Ok(symmetry) => self
+                .inner
+                .set_draw_symmetry(symmetry, (center_row, center_col)),
+            Err(e) => log(&e.to_string()),
         }
     }
 
-    pub fn cells(&self) -> *const Cell {
-        self.cells.as_ptr()
+    /// Draws the alive cells of an RLE pattern with its top-left corner at
+    /// `(row, col)`.
+    pub fn paste_rle(&mut self, pattern: &str, row: i32, col: i32) {
+        if let Err(e) = self.inner.paste_rle(pattern, (row, col)) {
+            log(&e.to_string());
+        }
+    }
+
+    /// Like the other drawing tools, draws in `state`: a `Cell`, or any state
+    /// of the running automaton.
+    pub fn draw_line(&mut self, row0: i32, col0: i32, row1: i32, col1: i32, state: u8) {
+        self.inner.draw_line((row0, col0), (row1, col1), state);
     }
 
-    fn alive_neighbors(&self, row: i32, col: i32) -> u8 {
-        let indexes = [
-            (-1, -1),
-            (-1, 0),
-            (-1, 1),
-            (0, -1),
-            (0, 1),
-            (1, -1),
-            (1, 0),
-            (1, 1),
-        ];
-        indexes
+    pub fn draw_rect(
+        &mut self,
+        row: i32,
+        col: i32,
+        width: i32,
+        height: i32,
+        state: u8,
+        filled: bool,
+    ) {
+        let rect = engine::Rect {
+            x: col,
+            y: row,
+            width,
+            height,
+        };
+        self.inner.draw_rect(&rect, state, fill(filled));
+    }
+
+    /// Draws the ellipse inscribed in the rectangle whose top-left corner is
+    /// at `(row, col)`.
+    pub fn draw_ellipse(
+        &mut self,
+        row: i32,
+        col: i32,
+        width: i32,
+        height: i32,
+        state: u8,
+        filled: bool,
+    ) {
+        let rect = engine::Rect {
+            x: col,
+            y: row,
+            width,
+            height,
+        };
+        self.inner.draw_ellipse(&rect, state, fill(filled));
+    }
+
+    /// Fills the region around `(row, col)`, whose cells are adjacent through
+    /// their edges for a `connectivity` of 4, or edges and corners for 8.
+    pub fn flood_fill(&mut self, row: i32, col: i32, state: u8, connectivity: u8) {
+        match connectivity.to_string().parse() {
+            Ok(connectivity) => self.inner.flood_fill((row, col), state, connectivity),
+            Err(e) => log(&e.to_string()),
+        }
+    }
+
+    /// Adds an ant at `(row, col)` facing `direction` (`north`, `east`,
+    /// `south` or `west`), running a turmite given in Golly's notation or
+    /// as turns such as `RL`.
+    pub fn add_ant(&mut self, turmite: &str, row: i32, col: i32, direction: &str) {
+        let ant = turmite.parse().and_then(|turmite| {
+            let direction = direction.parse()?;
+            self.inner.add_ant(turmite, (row, col), direction)
+        });
+        if let Err(e) = ant {
+            log(&e.to_string());
+        }
+    }
+
+    pub fn clear_ants(&mut self) {
+        self.inner.clear_ants();
+    }
+
+    /// The row and column of every ant, one after the other.
+    pub fn ant_positions(&self) -> Vec<i32> {
+        self.inner
+            .ants()
             .iter()
-            .map(|(r, c)| self.get(row + r, col + c) as u8)
-            .sum()
+            .flat_map(|ant| vec![ant.position().row, ant.position().col])
+            .collect()
+    }
+
+    pub fn cells(&self) -> *const engine::Cell {
+        self.inner.cells().as_ptr()
     }
 
-    pub fn changed_cells(&mut self) -> *const i32 {
-        self.changed_cells.as_ptr()
+    pub fn changed_cells(&self) -> *const i32 {
+        self.inner.changed_cells().as_ptr()
     }
 
     pub fn changed_cells_len(&self) -> usize {
-        self.changed_cells.len()
+        self.inner.changed_cells().len()
     }
 
     pub fn reset_changed_cells(&mut self) {
-        self.changed_cells.clear();
+        self.inner.reset_changed_cells();
     }
 
-    fn get_idx(&self, idx: usize) -> Cell {
-        self.cells[idx]
+    pub fn next_tick(&mut self) {
+        self.inner.next_tick();
     }
 
-    pub fn next_tick(&mut self) {
-        let mut cells_to_check = number_hashset::hashset((self.width * self.height) as usize);
-        self.changed_cells.iter().for_each(|idx| {
-            cells_to_check.insert(*idx);
-            cells_to_check.insert(*idx - 1);
-            cells_to_check.insert(*idx + 1);
-
-            cells_to_check.insert(*idx - self.width - 1);
-            cells_to_check.insert(*idx - self.width);
-            cells_to_check.insert(*idx - self.width + 1);
-
-            cells_to_check.insert(*idx + self.width - 1);
-            cells_to_check.insert(*idx + self.width);
-            cells_to_check.insert(*idx + self.width + 1);
-        });
+    /// Runs at most `max_generations` generations until the board repeats
+    /// itself. Returns the generation the cycle starts at, its period and
+    /// how many objects were taken off the edges of a plane, or nothing if
+    /// it didn't settle.
+    pub fn run_until_stable(&mut self, max_generations: u32) -> Vec<u32> {
+        match self.inner.run_until_stable(max_generations) {
+            Some(stable) => vec![
+                stable.generation,
+                stable.period,
+                stable.escaped.len() as u32,
+            ],
+            None => Vec::new(),
+        }
+    }
 
-        if cells_to_check.is_empty() {
-            cells_to_check.extend(0..(self.width * self.height));
+    /// Undoes the last generation of a reversible block rule, on a torus of
+    /// even width and height.
+    pub fn previous_tick(&mut self) {
+        if let Err(e) = self.inner.previous_tick() {
+            log(&e.to_string());
         }
+    }
 
-        let mut new_changed_cells = Vec::new();
-        cells_to_check.iter().for_each(|idx| {
-            let (row, col) = self.from_index(*idx);
-            let cell = self.get_idx(*idx as usize);
-            let neighbors = self.alive_neighbors(row, col);
-            let next_cell = match (cell, neighbors) {
-                (Cell::Alive, 2) | (_, 3) => Cell::Alive,
-                _ => Cell::Dead,
-            };
-            if cell != next_cell {
-                new_changed_cells.push(*idx);
-            }
-            self.set(row, col, next_cell);
-        });
+    pub fn set_age_tracking(&mut self, enabled: bool) {
+        self.inner.set_age_tracking(enabled);
+    }
+
+    pub fn age_tracking(&self) -> bool {
+        self.inner.age_tracking()
+    }
+
+    /// For an alive cell, the number of generations it has been alive; for a
+    /// dead cell, the number of generations since it died. Cells that have
+    /// not been alive since tracking started report `u32::MAX`, as does
+    /// every cell when tracking is disabled.
+    pub fn cell_age(&self, row: i32, col: i32) -> u32 {
+        self.inner.age((row, col)).unwrap_or(NEVER_ALIVE)
+    }
 
-        self.generations += 1;
+    /// Fills the ages buffer (see `cell_age`) and returns a pointer to its
+    /// `width * height` entries.
+    pub fn ages(&mut self) -> *const u32 {
+        let ages = self
+            .inner
+            .ages()
+            .map(|age| age.unwrap_or(NEVER_ALIVE))
+            .collect();
+        self.ages = ages;
+        self.ages.as_ptr()
+    }
 
-        ::std::mem::swap(&mut self.cells, &mut self.cache);
-        ::std::mem::swap(&mut self.changed_cells, &mut new_changed_cells);
+    /// Replaces the whole board with random cells, each one being alive with
+    /// probability `density`. The same seed always yields the same board.
+    pub fn randomize(&mut self, density: f64, seed: u32) {
+        self.inner.randomize(density, seed);
     }
 
-    fn from_index(&self, mut idx: i32) -> (i32, i32) {
-        if idx < 0 {
-            idx += self.width * self.height;
+    /// Draws a random soup whose base rectangle has its top-left corner at
+    /// `(row, col)`, and mirrors it according to `symmetry` (see `Symmetry`
+    /// for the accepted names). Depending on the symmetry, the soup extends
+    /// below and to the right of the base rectangle.
+    #[allow(clippy::too_many_arguments)]
+    pub fn random_soup(
+        &mut self,
+        row: i32,
+        col: i32,
+        width: i32,
+        height: i32,
+        density: f64,
+        symmetry: &str,
+        seed: u32,
+    ) {
+        match symmetry.parse() {
+            Ok(symmetry) => {
+                let rect = engine::Rect {
+                    x: col,
+                    y: row,
+                    width,
+                    height,
+                };
+                self.inner.random_soup(&rect, density, symmetry, seed);
+            }
+            Err(e) => log(&e.to_string()),
         }
-        let row = idx / self.width;
-        let col = idx % self.width;
-        (row, col)
     }
+}
 
-    pub fn new(width: i32, height: i32) -> World {
-        console_error_panic_hook::set_once();
+/// Lists the library patterns matching every non-empty criterion, as a JSON
+/// array of `{ id, name, author, comments, kind, period, width, height }`
+/// objects. `name` and `author` match case-insensitive substrings, `kind` is
+/// e.g. `oscillator` or `spaceship`, and a `period` of 0 matches any period.
+#[wasm_bindgen]
+pub fn find_patterns(name: &str, author: &str, kind: &str, period: u32) -> String {
+    let non_empty = |s: &str| Some(s.to_string()).filter(|s| !s.is_empty());
+    let kind = match non_empty(kind)
+        .map(|kind| kind.parse::<library::PatternKind>())
+        .transpose()
+    {
+        Ok(kind) => kind,
+        Err(e) => {
+            log(&e.to_string());
+            return "[]".to_string();
+        }
+    };
+    let filter = library::Filter {
+        name: non_empty(name),
+        author: non_empty(author),
+        kind,
+        period: Some(period).filter(|p| *p > 0),
+    };
+    serde_json::to_string(&library::search(&filter)).expect("Pattern infos are serializable")
+}
 
-        let data = vec![Cell::Dead; (width * height) as usize];
+/// The RLE text of the library pattern `id`, or an empty string if there is
+/// no such pattern.
+#[wasm_bindgen]
+pub fn pattern_rle(id: &str) -> String {
+    library::pattern(id)
+        .map(|pattern| pattern.rle.to_string())
+        .unwrap_or_default()
+}
 
-        World {
-            width,
-            height,
-            cells: data.clone(),
-            cache: data,
-            generations: 0,
-            changed_cells: Vec::new(),
+/// Draws a `World` into an RGBA framebuffer living in wasm memory, so the
+/// whole canvas can be updated with a single `putImageData` call.
+#[wasm_bindgen]
+pub struct Renderer {
+    inner: engine::Renderer,
+}
+
+#[wasm_bindgen]
+impl Renderer {
+    pub fn new(width: u32, height: u32, cell_size: u32) -> Renderer {
+        Renderer {
+            inner: engine::Renderer::new(width, height, cell_size),
         }
     }
 
-    pub fn from_image(data: Vec<u8>) -> World {
-        let im = image::load_from_memory(&data)
-            .expect("Invalid image data")
-            .grayscale();
-        let mut world = World::new(im.width() as i32, im.height() as i32);
-        for pixel in im.pixels() {
-            let (x, y, pix) = pixel;
-            world.set_cell(y as i32, x as i32, Cell::from(pix));
-        }
-        world
+    pub fn width(&self) -> u32 {
+        self.inner.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.inner.height()
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.inner.resize(width, height);
+    }
+
+    pub fn cell_size(&self) -> u32 {
+        self.inner.cell_size()
+    }
+
+    pub fn set_cell_size(&mut self, cell_size: u32) {
+        self.inner.set_cell_size(cell_size);
+    }
+
+    pub fn set_grid(&mut self, grid: bool) {
+        self.inner.set_grid(grid);
+    }
+
+    /// Colors are `0xRRGGBB` integers.
+    pub fn set_alive_color(&mut self, color: u32) {
+        self.inner.set_alive_color(color);
+    }
+
+    pub fn set_dead_color(&mut self, color: u32) {
+        self.inner.set_dead_color(color);
+    }
+
+    pub fn set_grid_color(&mut self, color: u32) {
+        self.inner.set_grid_color(color);
+    }
+
+    pub fn set_color_by_age(&mut self, enabled: bool) {
+        self.inner.set_color_by_age(enabled);
+    }
+
+    pub fn set_newborn_color(&mut self, color: u32) {
+        self.inner.set_newborn_color(color);
+    }
+
+    pub fn set_dying_color(&mut self, color: u32) {
+        self.inner.set_dying_color(color);
+    }
+
+    pub fn set_ant_color(&mut self, color: u32) {
+        self.inner.set_ant_color(color);
+    }
+
+    /// `0xRRGGBB` colors of the states of an automaton, from state 0.
+    pub fn set_palette(&mut self, colors: Vec<u32>) {
+        self.inner.set_palette(&colors);
+    }
+
+    pub fn set_viewport(&mut self, row: i32, col: i32) {
+        self.inner.set_viewport(row, col);
+    }
+
+    pub fn pan(&mut self, rows: i32, cols: i32) {
+        self.inner.pan(rows, cols);
+    }
+
+    /// Converts a canvas position in pixels to the `[row, col]` of the world
+    /// cell drawn there.
+    pub fn cell_at(&self, world: &World, x: u32, y: u32) -> Vec<i32> {
+        let at = self.inner.cell_at(&world.inner, x, y);
+        vec![at.row, at.col]
+    }
+
+    pub fn invalidate(&mut self) {
+        self.inner.invalidate();
+    }
+
+    pub fn render(&mut self, world: &World) {
+        self.inner.render(&world.inner);
+    }
+
+    pub fn pixels(&self) -> *const u8 {
+        self.inner.framebuffer().as_ptr()
+    }
+
+    pub fn pixels_len(&self) -> usize {
+        self.inner.framebuffer().len()
     }
 }